@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// Reasons a filter refuses to touch a buffer.
+/// The `wasm` shim converts these into a JS `Error` whose `code` is the [`ErrorKind`], so
+/// callers can `try/catch` them and branch on the kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageError {
+    /// Width or height is zero, or below the minimum the filter's kernel needs.
+    InvalidDimensions { width: u32, height: u32, min: u32 },
+    /// The buffer (or the `alloc` block a pointer belongs to) cannot hold the image.
+    BufferTooSmall { needed: usize, available: usize },
+    /// `width * height * 4` does not fit in `usize`.
+    Overflow,
+    /// Row stride is shorter than one row of pixels.
+    InvalidStride { stride: usize, min: usize },
+    NullPointer,
+    /// A filter parameter is out of range (e.g. a non-positive sigma).
+    InvalidParameter { name: &'static str },
+}
+
+/// Variant of an [`ImageError`] without its details, e.g. for branching on the `code` of a
+/// JS exception.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    InvalidDimensions = 0,
+    BufferTooSmall = 1,
+    Overflow = 2,
+    InvalidStride = 3,
+    NullPointer = 4,
+    InvalidParameter = 5,
+}
+
+impl ImageError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ImageError::InvalidDimensions { .. } => ErrorKind::InvalidDimensions,
+            ImageError::BufferTooSmall { .. } => ErrorKind::BufferTooSmall,
+            ImageError::Overflow => ErrorKind::Overflow,
+            ImageError::InvalidStride { .. } => ErrorKind::InvalidStride,
+            ImageError::NullPointer => ErrorKind::NullPointer,
+            ImageError::InvalidParameter { .. } => ErrorKind::InvalidParameter,
+        }
+    }
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ImageError::InvalidDimensions { width, height, min } =>
+                write!(f, "invalid image dimensions {}x{} (minimum {}x{})", width, height, min, min),
+            ImageError::BufferTooSmall { needed, available } =>
+                write!(f, "buffer too small: need {} bytes, have {}", needed, available),
+            ImageError::Overflow => write!(f, "image size overflows the address space"),
+            ImageError::InvalidStride { stride, min } =>
+                write!(f, "row stride {} is shorter than a row of pixels ({} bytes)", stride, min),
+            ImageError::NullPointer => write!(f, "null image pointer"),
+            ImageError::InvalidParameter { name } => write!(f, "invalid value for `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
@@ -1,14 +1,48 @@
-use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
-#[wasm_bindgen]
-pub fn gaussian_blur(ptr: *mut u8, width: u32, height: u32) {
-    let size = (width * height * 4) as usize;
-    let mem = unsafe { std::slice::from_raw_parts_mut(ptr, size) };
+use crate::error::ImageError;
+use crate::filters::border::BorderMode;
+use crate::image::RgbaImageMut;
+use crate::profile::Profiler;
 
-    let kernel_size = 25;
-    let radius = kernel_size / 2;
-    let sigma = 10.0;
+/// Kernel used by the original benchmark: 25x25 taps (radius 12), sigma 10. Matches `gaussianBlurJS`.
+pub const DEFAULT_SIGMA: f64 = 10.0;
+pub const DEFAULT_RADIUS: usize = 12;
 
+/// Distinct (sigma, radius) kernels kept around; a slider sweep would otherwise grow the cache forever.
+const KERNEL_CACHE_CAPACITY: usize = 16;
+
+/// Upper bound on the kernel radius, keeping `(2r + 1)^2` taps and the `i32` index math sane.
+pub const MAX_RADIUS: usize = 1024;
+
+/// Kernels keyed by `(sigma.to_bits(), radius)`.
+type KernelCache = HashMap<(u64, usize), Rc<[f64]>>;
+
+thread_local! {
+    static KERNEL_CACHE: RefCell<KernelCache> = RefCell::new(HashMap::new());
+}
+
+/// Radius that covers +-3 sigma, i.e. >99.7% of the Gaussian's mass.
+pub fn default_radius(sigma: f64) -> usize {
+    (3.0 * sigma).ceil() as usize
+}
+
+/// Unnormalized Gaussian weight at squared distance `d2`. The centre tap is exactly 1, so a
+/// sigma small enough for `2 * sigma^2` to underflow gives the identity kernel, not `0 / 0`.
+fn gaussian_weight(d2: f64, sigma: f64) -> f64 {
+    if d2 == 0.0 { 1.0 } else { (-d2 / (2.0 * sigma * sigma)).exp() }
+}
+
+/// Normalized 2-D Gaussian kernel of `(2 * radius + 1)^2` taps, built once per (sigma, radius).
+fn gaussian_kernel_2d(sigma: f64, radius: usize) -> Rc<[f64]> {
+    let key = (sigma.to_bits(), radius);
+    if let Some(kernel) = KERNEL_CACHE.with(|cache| cache.borrow().get(&key).cloned()) {
+        return kernel;
+    }
+
+    let kernel_size = 2 * radius + 1;
     let mut kernel = vec![0.0; kernel_size * kernel_size];
     let mut sum = 0.0;
 
@@ -17,7 +51,7 @@ pub fn gaussian_blur(ptr: *mut u8, width: u32, height: u32) {
         for x in 0..kernel_size {
             let dx = ((x as i32) - (radius as i32)) as f64;
             let dy = ((y as i32) - (radius as i32)) as f64;
-            let value = (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp();
+            let value = gaussian_weight(dx * dx + dy * dy, sigma);
             kernel[y * kernel_size + x] = value;
             sum += value;
         }
@@ -26,7 +60,137 @@ pub fn gaussian_blur(ptr: *mut u8, width: u32, height: u32) {
         *v /= sum;
     }
 
-    let original = mem.to_vec(); // make a copy to read from
+    let kernel: Rc<[f64]> = kernel.into();
+    KERNEL_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.len() >= KERNEL_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(key, kernel.clone());
+    });
+    kernel
+}
+
+/// Original fixed-strength blur (sigma 10, 25x25 kernel).
+pub fn gaussian_blur(img: &mut RgbaImageMut) {
+    let mut profiler = Profiler::start("gaussian_blur");
+    blur_2d(img, DEFAULT_SIGMA, DEFAULT_RADIUS, BorderMode::Clamp);
+    profiler.stage("blur");
+}
+
+/// How the Gaussian is evaluated. All variants approximate the same blur for a given sigma;
+/// they differ only in cost, which is what the benchmark compares.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlurAlgorithm {
+    /// Direct 2-D convolution, O(r^2) per pixel. Same as the original `gaussian_blur`.
+    Naive = 0,
+    /// Horizontal then vertical 1-D convolution, O(r) per pixel.
+    Separable = 1,
+    /// Three successive box blurs using running sums, O(1) per pixel. Ignores `radius`.
+    Box = 2,
+    /// Young-van Vliet recursive (IIR) filter, O(1) per pixel. Ignores `radius`. Below
+    /// sigma 0.5, where the recursion is not defined, falls back to `Separable`.
+    Recursive = 3,
+}
+
+/// Gaussian blur with a caller-chosen `sigma`; `radius` defaults to `ceil(3 * sigma)`.
+pub fn gaussian_blur_with(
+    img: &mut RgbaImageMut,
+    sigma: f64,
+    radius: Option<usize>
+) -> Result<(), ImageError> {
+    gaussian_blur_with_algorithm(img, sigma, radius, BlurAlgorithm::Naive, BorderMode::Clamp)
+}
+
+/// Single entry point for every blur implementation, see [`BlurAlgorithm`] and [`BorderMode`].
+pub fn gaussian_blur_with_algorithm(
+    img: &mut RgbaImageMut,
+    sigma: f64,
+    radius: Option<usize>,
+    algorithm: BlurAlgorithm,
+    border: BorderMode
+) -> Result<(), ImageError> {
+    if !sigma.is_finite() || sigma <= 0.0 {
+        return Err(ImageError::InvalidParameter { name: "sigma" });
+    }
+    let radius = radius.unwrap_or_else(|| default_radius(sigma));
+    if radius > MAX_RADIUS {
+        return Err(ImageError::InvalidParameter { name: "radius" });
+    }
+    // Box and Recursive ignore `radius` and reach about 3 sigma; the line padding grows with it
+    if matches!(algorithm, BlurAlgorithm::Box | BlurAlgorithm::Recursive) && default_radius(sigma) > MAX_RADIUS {
+        return Err(ImageError::InvalidParameter { name: "sigma" });
+    }
+    let mut profiler = Profiler::start("gaussian_blur");
+
+    // Skip: blur as if clamped, then put back every pixel whose window reached past the edge
+    let original = if border == BorderMode::Skip { Some(img.to_packed()) } else { None };
+
+    let separable = |img: &mut RgbaImageMut, radius: usize| {
+        let kernel = gaussian_kernel_1d(sigma, radius);
+        blur_lines(img, border, radius, |src, dst| convolve_line(src, dst, &kernel));
+        radius
+    };
+    let reach = match algorithm {
+        BlurAlgorithm::Naive => {
+            blur_2d(img, sigma, radius, border);
+            radius
+        }
+        BlurAlgorithm::Separable => separable(img, radius),
+        BlurAlgorithm::Box => {
+            let radii = box_radii_for_gauss(sigma);
+            let reach = radii.iter().sum();
+            let mut tmp = Vec::new();
+            blur_lines(img, border, reach, |src, dst| box_blur_line(src, dst, &radii, &mut tmp));
+            reach
+        }
+        // Young-van Vliet is only defined from sigma 0.5 on
+        BlurAlgorithm::Recursive if sigma < YoungVanVliet::MIN_SIGMA => separable(img, default_radius(sigma)),
+        BlurAlgorithm::Recursive => {
+            // The IIR response never reaches zero; 3 sigma holds all but ~0.3% of its weight
+            let reach = default_radius(sigma);
+            let coeffs = YoungVanVliet::new(sigma);
+            let mut tmp = Vec::new();
+            blur_lines(img, border, reach, |src, dst| coeffs.filter_line(src, dst, &mut tmp));
+            reach
+        }
+    };
+    profiler.stage("blur");
+
+    if let Some(original) = original {
+        restore_border_band(img, &original, reach);
+        profiler.stage("restore border");
+    }
+    Ok(())
+}
+
+/// Copy back the original pixels within `band` pixels of any edge.
+fn restore_border_band(img: &mut RgbaImageMut, original: &[u8], band: usize) {
+    let w = img.width() as usize;
+    let h = img.height() as usize;
+    for y in 0..h {
+        let src = &original[y * w * 4..(y + 1) * w * 4];
+        let row = img.row_mut(y);
+        if y < band || y + band >= h {
+            row.copy_from_slice(src);
+            continue;
+        }
+        let edge = band.min(w) * 4;
+        row[..edge].copy_from_slice(&src[..edge]);
+        let tail = w.saturating_sub(band) * 4;
+        row[tail..].copy_from_slice(&src[tail..]);
+    }
+}
+
+fn blur_2d(img: &mut RgbaImageMut, sigma: f64, radius: usize, border: BorderMode) {
+    let width = img.width() as usize;
+    let height = img.height() as usize;
+
+    let kernel_size = 2 * radius + 1;
+    let kernel = gaussian_kernel_2d(sigma, radius);
+
+    let original = img.to_packed(); // make a copy to read from
 
     for y in 0..height {
         for x in 0..width {
@@ -37,18 +201,20 @@ pub fn gaussian_blur(ptr: *mut u8, width: u32, height: u32) {
 
             for ky in 0..kernel_size {
                 for kx in 0..kernel_size {
-                    let ix = ((x as i32) + (kx as i32) - (radius as i32)).clamp(
-                        0,
-                        (width - 1) as i32
-                    ) as u32;
-                    let iy = ((y as i32) + (ky as i32) - (radius as i32)).clamp(
-                        0,
-                        (height - 1) as i32
-                    ) as u32;
-
-                    let idx = ((iy * width + ix) * 4) as usize;
+                    let ix = border.index((x + kx) as isize - (radius as isize), width);
+                    let iy = border.index((y + ky) as isize - (radius as isize), height);
                     let weight = kernel[ky * kernel_size + kx];
 
+                    let (Some(ix), Some(iy)) = (ix, iy) else {
+                        let c = (border.constant() as f64) * weight;
+                        r += c;
+                        g += c;
+                        b += c;
+                        a += c;
+                        continue;
+                    };
+
+                    let idx = (iy * width + ix) * 4;
                     r += (original[idx] as f64) * weight;
                     g += (original[idx + 1] as f64) * weight;
                     b += (original[idx + 2] as f64) * weight;
@@ -56,11 +222,255 @@ pub fn gaussian_blur(ptr: *mut u8, width: u32, height: u32) {
                 }
             }
 
-            let out_idx = ((y * width + x) * 4) as usize;
+            let out_idx = img.offset(x, y);
+            let mem = img.data_mut();
             mem[out_idx] = r.round().clamp(0.0, 255.0) as u8;
             mem[out_idx + 1] = g.round().clamp(0.0, 255.0) as u8;
             mem[out_idx + 2] = b.round().clamp(0.0, 255.0) as u8;
             mem[out_idx + 3] = a.round().clamp(0.0, 255.0) as u8;
         }
     }
-}
\ No newline at end of file
+}
+/// Normalized 1-D Gaussian kernel of `2 * radius + 1` taps.
+fn gaussian_kernel_1d(sigma: f64, radius: usize) -> Vec<f64> {
+    let mut kernel: Vec<f64> = (0..2 * radius + 1)
+        .map(|i| {
+            let d = (i as f64) - (radius as f64);
+            gaussian_weight(d * d, sigma)
+        })
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+    for v in kernel.iter_mut() {
+        *v /= sum;
+    }
+    kernel
+}
+
+/// Run a 1-D line filter over every row and then every column of each RGBA channel.
+/// Each line is padded with `pad` extrapolated samples per side according to `border`,
+/// so the filters themselves never look outside their input.
+/// Lines are filtered in f64; between the two passes one channel at a time is kept as an
+/// f32 plane, so the scratch memory is the size of the image rather than eight times it.
+/// Values are only rounded once at the end.
+fn blur_lines(
+    img: &mut RgbaImageMut,
+    border: BorderMode,
+    pad: usize,
+    mut filter: impl FnMut(&[f64], &mut [f64])
+) {
+    let w = img.width() as usize;
+    let h = img.height() as usize;
+    let mut plane = vec![0.0f32; w * h];
+    let n_max = w.max(h);
+    let mut line = vec![0.0f64; n_max];
+    let mut src = vec![0.0f64; n_max + 2 * pad];
+    let mut dst = vec![0.0f64; n_max + 2 * pad];
+
+    for c in 0..4 {
+        // Horizontal
+        for y in 0..h {
+            for (v, px) in line.iter_mut().zip(img.row(y).chunks_exact(4)) {
+                *v = px[c] as f64;
+            }
+            pad_line(&line[..w], &mut src[..w + 2 * pad], pad, border);
+            filter(&src[..w + 2 * pad], &mut dst[..w + 2 * pad]);
+            for (p, &v) in plane[y * w..(y + 1) * w].iter_mut().zip(&dst[pad..pad + w]) {
+                *p = v as f32;
+            }
+        }
+
+        // Vertical, writing the channel back
+        for x in 0..w {
+            for y in 0..h {
+                line[y] = plane[y * w + x] as f64;
+            }
+            pad_line(&line[..h], &mut src[..h + 2 * pad], pad, border);
+            filter(&src[..h + 2 * pad], &mut dst[..h + 2 * pad]);
+            for y in 0..h {
+                let o = img.offset(x, y) + c;
+                img.data_mut()[o] = dst[pad + y].round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+/// Fill `out` with `line` surrounded by `pad` extrapolated samples on each side.
+fn pad_line(line: &[f64], out: &mut [f64], pad: usize, border: BorderMode) {
+    for (i, v) in out.iter_mut().enumerate() {
+        *v = border.sample(line, (i as isize) - (pad as isize));
+    }
+}
+
+#[inline]
+fn clamped(line: &[f64], i: isize) -> f64 {
+    line[i.clamp(0, (line.len() as isize) - 1) as usize]
+}
+
+fn convolve_line(src: &[f64], dst: &mut [f64], kernel: &[f64]) {
+    let r = ((kernel.len() - 1) / 2) as isize;
+    for (i, out) in dst.iter_mut().enumerate() {
+        let mut acc = 0.0;
+        for (k, &weight) in kernel.iter().enumerate() {
+            acc += clamped(src, (i as isize) + (k as isize) - r) * weight;
+        }
+        *out = acc;
+    }
+}
+
+/// Radii of three box filters whose successive application approximates a Gaussian of `sigma`
+/// (Kovesi, "Fast almost-Gaussian filtering"). Widths are odd and differ by at most 2.
+fn box_radii_for_gauss(sigma: f64) -> [usize; 3] {
+    const PASSES: f64 = 3.0;
+    let w_ideal = ((12.0 * sigma * sigma) / PASSES + 1.0).sqrt();
+    let mut wl = w_ideal.floor() as i64;
+    if wl % 2 == 0 {
+        wl -= 1;
+    }
+    let wu = wl + 2;
+    let wlf = wl as f64;
+    let m_ideal =
+        (12.0 * sigma * sigma - PASSES * wlf * wlf - 4.0 * PASSES * wlf - 3.0 * PASSES) /
+        (-4.0 * wlf - 4.0);
+    let m = m_ideal.round() as i64;
+
+    let mut radii = [0usize; 3];
+    for (i, r) in radii.iter_mut().enumerate() {
+        let width = if (i as i64) < m { wl } else { wu };
+        *r = (width.max(1) as usize - 1) / 2;
+    }
+    radii
+}
+
+/// Apply each box radius in turn using a running sum, so the cost does not depend on the radius.
+fn box_blur_line(src: &[f64], dst: &mut [f64], radii: &[usize; 3], tmp: &mut Vec<f64>) {
+    tmp.clear();
+    tmp.extend_from_slice(src);
+    for &r in radii {
+        let r = r as isize;
+        let inv = 1.0 / ((2 * r + 1) as f64);
+        let mut acc: f64 = (-r..=r).map(|i| clamped(tmp, i)).sum();
+        for (i, out) in dst.iter_mut().enumerate() {
+            let i = i as isize;
+            *out = acc * inv;
+            acc += clamped(tmp, i + r + 1) - clamped(tmp, i - r);
+        }
+        tmp.copy_from_slice(dst);
+    }
+}
+
+/// Coefficients of the Young-van Vliet third-order recursive Gaussian
+/// ("Recursive implementation of the Gaussian filter", 1995).
+struct YoungVanVliet {
+    b: f64,
+    b1: f64,
+    b2: f64,
+    b3: f64,
+}
+
+impl YoungVanVliet {
+    /// Smallest sigma the fitted `q` formula is valid for; below it `q` turns negative.
+    const MIN_SIGMA: f64 = 0.5;
+
+    fn new(sigma: f64) -> YoungVanVliet {
+        debug_assert!(sigma >= YoungVanVliet::MIN_SIGMA);
+        let q = if sigma >= 2.5 {
+            0.98711 * sigma - 0.9633
+        } else {
+            3.97156 - 4.14554 * (1.0 - 0.26891 * sigma).sqrt()
+        };
+        let q2 = q * q;
+        let q3 = q2 * q;
+        let b0 = 1.57825 + 2.44413 * q + 1.4281 * q2 + 0.422205 * q3;
+        let b1 = (2.44413 * q + 2.85619 * q2 + 1.26661 * q3) / b0;
+        let b2 = -(1.4281 * q2 + 1.26661 * q3) / b0;
+        let b3 = (0.422205 * q3) / b0;
+        YoungVanVliet { b: 1.0 - (b1 + b2 + b3), b1, b2, b3 }
+    }
+
+    /// Causal pass left-to-right, then anti-causal pass right-to-left.
+    /// Both start from the steady state of the edge pixel, which matches clamped borders.
+    fn filter_line(&self, src: &[f64], dst: &mut [f64], tmp: &mut Vec<f64>) {
+        let n = src.len();
+        tmp.clear();
+        tmp.resize(n, 0.0);
+
+        let (mut p1, mut p2, mut p3) = (src[0], src[0], src[0]);
+        for i in 0..n {
+            let v = self.b * src[i] + self.b1 * p1 + self.b2 * p2 + self.b3 * p3;
+            tmp[i] = v;
+            p3 = p2;
+            p2 = p1;
+            p1 = v;
+        }
+
+        let last = tmp[n - 1];
+        let (mut p1, mut p2, mut p3) = (last, last, last);
+        for i in (0..n).rev() {
+            let v = self.b * tmp[i] + self.b1 * p1 + self.b2 * p2 + self.b3 * p3;
+            dst[i] = v;
+            p3 = p2;
+            p2 = p1;
+            p1 = v;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(w: usize, h: usize) -> Vec<u8> {
+        (0..w * h * 4).map(|i| ((i * 37 + (i / (w * 4)) * 91) % 256) as u8).collect()
+    }
+
+    fn blurred(sigma: f64, radius: Option<usize>, algorithm: BlurAlgorithm) -> Result<Vec<u8>, ImageError> {
+        let mut data = pattern(9, 7);
+        gaussian_blur_with_algorithm(
+            &mut RgbaImageMut::new(&mut data, 9, 7).unwrap(),
+            sigma,
+            radius,
+            algorithm,
+            BorderMode::Reflect101
+        )?;
+        Ok(data)
+    }
+
+    #[test]
+    fn reach_from_sigma_is_bounded() {
+        let sigma = 1e6;
+        for algorithm in [BlurAlgorithm::Box, BlurAlgorithm::Recursive] {
+            assert_eq!(blurred(sigma, Some(2), algorithm).err(), Some(ImageError::InvalidParameter { name: "sigma" }));
+        }
+        // The kernel-based paths only reach `radius`
+        assert!(blurred(sigma, Some(2), BlurAlgorithm::Separable).is_ok());
+        assert_eq!(
+            blurred(1.0, Some(MAX_RADIUS + 1), BlurAlgorithm::Naive).err(),
+            Some(ImageError::InvalidParameter { name: "radius" })
+        );
+    }
+
+    #[test]
+    fn vanishing_sigma_leaves_the_image_unchanged() {
+        for algorithm in [BlurAlgorithm::Naive, BlurAlgorithm::Separable, BlurAlgorithm::Box, BlurAlgorithm::Recursive] {
+            for radius in [None, Some(3)] {
+                assert_eq!(blurred(1e-300, radius, algorithm).unwrap(), pattern(9, 7), "{:?} {:?}", algorithm, radius);
+            }
+        }
+    }
+
+    #[test]
+    fn recursive_falls_back_to_separable_below_half_sigma() {
+        let sigma = 0.3;
+        assert_eq!(
+            blurred(sigma, None, BlurAlgorithm::Recursive).unwrap(),
+            blurred(sigma, None, BlurAlgorithm::Separable).unwrap()
+        );
+    }
+
+    #[test]
+    fn separable_matches_naive() {
+        let naive = blurred(1.5, None, BlurAlgorithm::Naive).unwrap();
+        let separable = blurred(1.5, None, BlurAlgorithm::Separable).unwrap();
+        assert!(naive.iter().zip(&separable).all(|(&a, &b)| a.abs_diff(b) <= 1));
+    }
+}
@@ -0,0 +1,156 @@
+/// How convolution filters sample pixels outside the image.
+/// Names and semantics follow OpenCV's `BORDER_*` constants (`|` marks the image edge):
+///
+/// ```text
+/// Clamp       aaaa|abcdefgh|hhhh
+/// Reflect     dcba|abcdefgh|hgfe
+/// Reflect101  edcb|abcdefgh|gfed
+/// Wrap        efgh|abcdefgh|abcd
+/// Constant(i) iiii|abcdefgh|iiii
+/// ```
+///
+/// `Skip` does not extrapolate at all: pixels whose kernel window leaves the image are not
+/// filtered. Blurs leave them unchanged, edge detectors report no edge there.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BorderMode {
+    Clamp,
+    Reflect,
+    Reflect101,
+    Wrap,
+    Constant(u8),
+    Skip,
+}
+
+impl BorderMode {
+    /// Map coordinate `i` onto `0..n`. `None` means "use the constant value".
+    /// `Skip` maps like `Clamp`; callers are expected to not use the result for skipped pixels.
+    #[inline]
+    pub fn index(self, i: isize, n: usize) -> Option<usize> {
+        let last = (n as isize) - 1;
+        if (0..=last).contains(&i) {
+            return Some(i as usize);
+        }
+        match self {
+            BorderMode::Clamp | BorderMode::Skip => Some(i.clamp(0, last) as usize),
+            BorderMode::Reflect => Some(reflect(i, n, 0)),
+            BorderMode::Reflect101 => Some(reflect(i, n, 1)),
+            BorderMode::Wrap => Some(i.rem_euclid(n as isize) as usize),
+            BorderMode::Constant(_) => None,
+        }
+    }
+
+    /// Value of the sample at `i` in `line`, extrapolated according to the mode.
+    #[inline]
+    pub fn sample<T: Copy + From<u8>>(self, line: &[T], i: isize) -> T {
+        match self.index(i, line.len()) {
+            Some(j) => line[j],
+            None => T::from(self.constant()),
+        }
+    }
+
+    /// Value at `(x, y)` of a row-major `w x h` plane, extrapolated on both axes.
+    #[inline]
+    pub fn sample_2d<T: Copy + From<u8>>(self, plane: &[T], w: usize, h: usize, x: isize, y: isize) -> T {
+        match (self.index(x, w), self.index(y, h)) {
+            (Some(x), Some(y)) => plane[y * w + x],
+            _ => T::from(self.constant()),
+        }
+    }
+
+    /// Value used for out-of-range samples in `Constant` mode (0 otherwise).
+    #[inline]
+    pub fn constant(self) -> u8 {
+        match self {
+            BorderMode::Constant(v) => v,
+            _ => 0,
+        }
+    }
+}
+
+/// Mirror `i` back into `0..n`, repeating for offsets larger than the image.
+/// `delta` is 0 for `Reflect` (edge pixel repeated) and 1 for `Reflect101` (not repeated).
+fn reflect(mut i: isize, n: usize, delta: isize) -> usize {
+    let n = n as isize;
+    if n == 1 {
+        return 0;
+    }
+    loop {
+        if i < 0 {
+            i = -i - 1 + delta;
+        } else if i >= n {
+            i = 2 * n - i - 1 - delta;
+        } else {
+            return i as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [BorderMode; 5] =
+        [BorderMode::Clamp, BorderMode::Reflect, BorderMode::Reflect101, BorderMode::Wrap, BorderMode::Constant(9)];
+
+    /// OpenCV `borderInterpolate` written as the periodic extension of each pattern.
+    fn reference(mode: BorderMode, i: isize, n: usize) -> Option<usize> {
+        let n = n as isize;
+        let j = match mode {
+            _ if (0..n).contains(&i) => i,
+            BorderMode::Clamp | BorderMode::Skip => i.clamp(0, n - 1),
+            // Periods of 2n (edge pixel repeated) and 2n - 2 (not repeated)
+            BorderMode::Reflect => {
+                let p = i.rem_euclid(2 * n);
+                if p < n { p } else { 2 * n - 1 - p }
+            }
+            BorderMode::Reflect101 if n == 1 => 0,
+            BorderMode::Reflect101 => {
+                let p = i.rem_euclid(2 * n - 2);
+                if p < n { p } else { 2 * n - 2 - p }
+            }
+            BorderMode::Wrap => i.rem_euclid(n),
+            BorderMode::Constant(_) => return None,
+        };
+        Some(j as usize)
+    }
+
+    #[test]
+    fn indices_follow_opencv_around_an_eight_pixel_line() {
+        let n = 8;
+        // -1, -2, n, n + 1, -3n and 2n + 1
+        let at = [-1, -2, 8, 9, -24, 17];
+        let expected = [
+            (BorderMode::Clamp, [0, 0, 7, 7, 0, 7]),
+            (BorderMode::Reflect, [0, 1, 7, 6, 7, 1]),
+            (BorderMode::Reflect101, [1, 2, 6, 5, 4, 3]),
+            (BorderMode::Wrap, [7, 6, 0, 1, 0, 1]),
+        ];
+        for (mode, indices) in expected {
+            let got: Vec<usize> = at.iter().map(|&i| mode.index(i, n).unwrap()).collect();
+            assert_eq!(got, indices, "{:?}", mode);
+        }
+        assert!(at.iter().all(|&i| BorderMode::Constant(9).index(i, n).is_none()));
+        assert_eq!(BorderMode::Constant(9).sample(&[1u8, 2, 3], -1), 9);
+    }
+
+    #[test]
+    fn indices_match_the_periodic_extension_far_outside() {
+        for n in 1..=5usize {
+            let n_i = n as isize;
+            for i in -3 * n_i..=2 * n_i + 1 {
+                for mode in MODES {
+                    assert_eq!(mode.index(i, n), reference(mode, i, n), "{:?} i={} n={}", mode, i, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_pixel_lines_always_map_to_it() {
+        for mode in MODES.into_iter().filter(|&m| m != BorderMode::Constant(9)) {
+            for i in [-3, -2, -1, 1, 2, 3] {
+                assert_eq!(mode.index(i, 1), Some(0), "{:?} i={}", mode, i);
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use crate::error::ImageError;
+use crate::image::RgbaImageMut;
+use crate::profile::Profiler;
+
+/// 4x5 colour matrix in row-major order, as in SVG `feColorMatrix`: row `i` gives output
+/// channel `i` (R, G, B, A) as `m[5i] R + m[5i+1] G + m[5i+2] B + m[5i+3] A + m[5i+4]`,
+/// with channels and the offset column in 0..1 units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorMatrix {
+    values: [f32; 20],
+}
+
+impl ColorMatrix {
+    pub const IDENTITY: ColorMatrix = ColorMatrix {
+        values: [
+            1.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ],
+    };
+
+    /// Fails with `InvalidParameter("matrix")` on NaN or infinite entries.
+    pub fn new(values: [f32; 20]) -> Result<ColorMatrix, ImageError> {
+        if values.iter().any(|v| !v.is_finite()) {
+            return Err(ImageError::InvalidParameter { name: "matrix" });
+        }
+        Ok(ColorMatrix { values })
+    }
+
+    /// Microsoft sepia coefficients, the same as [`sepia`](crate::filters::sepia::sepia) and CSS `sepia()`.
+    pub fn sepia() -> ColorMatrix {
+        ColorMatrix::rgb([0.393, 0.769, 0.189, 0.349, 0.686, 0.168, 0.272, 0.534, 0.131])
+    }
+
+    /// Rec. 709 luma in every channel, as CSS `grayscale()`.
+    pub fn grayscale() -> ColorMatrix {
+        ColorMatrix::rgb([0.2126, 0.7152, 0.0722, 0.2126, 0.7152, 0.0722, 0.2126, 0.7152, 0.0722])
+    }
+
+    /// `1 - c` for R, G and B; alpha is kept.
+    pub fn invert() -> ColorMatrix {
+        let mut m = ColorMatrix::rgb([-1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, -1.0]);
+        for row in 0..3 {
+            m.values[row * 5 + 4] = 1.0;
+        }
+        m
+    }
+
+    /// `feColorMatrix type="saturate"`: 0 is grayscale, 1 unchanged, above 1 oversaturated.
+    pub fn saturate(s: f32) -> Result<ColorMatrix, ImageError> {
+        if !s.is_finite() || s < 0.0 {
+            return Err(ImageError::InvalidParameter { name: "saturation" });
+        }
+        Ok(ColorMatrix::rgb([
+            0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s,
+            0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s,
+            0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s,
+        ]))
+    }
+
+    /// `feColorMatrix type="hueRotate"`: rotate hues by `degrees` around the luma axis.
+    pub fn hue_rotate(degrees: f32) -> Result<ColorMatrix, ImageError> {
+        if !degrees.is_finite() {
+            return Err(ImageError::InvalidParameter { name: "degrees" });
+        }
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Ok(ColorMatrix::rgb([
+            0.213 + cos * 0.787 - sin * 0.213,
+            0.715 - cos * 0.715 - sin * 0.715,
+            0.072 - cos * 0.072 + sin * 0.928,
+            0.213 - cos * 0.213 + sin * 0.143,
+            0.715 + cos * 0.285 + sin * 0.140,
+            0.072 - cos * 0.072 - sin * 0.283,
+            0.213 - cos * 0.213 - sin * 0.787,
+            0.715 - cos * 0.715 + sin * 0.715,
+            0.072 + cos * 0.928 + sin * 0.072,
+        ]))
+    }
+
+    /// Blend with the identity: 0 leaves the image unchanged, 1 applies the full matrix.
+    pub fn with_intensity(&self, intensity: f32) -> Result<ColorMatrix, ImageError> {
+        if !(0.0..=1.0).contains(&intensity) {
+            return Err(ImageError::InvalidParameter { name: "intensity" });
+        }
+        let mut values = self.values;
+        for (v, id) in values.iter_mut().zip(&ColorMatrix::IDENTITY.values) {
+            *v = id + (*v - id) * intensity;
+        }
+        Ok(ColorMatrix { values })
+    }
+
+    pub fn values(&self) -> &[f32; 20] {
+        &self.values
+    }
+
+    /// Matrix acting on R, G and B only (row-major 3x3), alpha passed through.
+    fn rgb(m: [f32; 9]) -> ColorMatrix {
+        let mut values = ColorMatrix::IDENTITY.values;
+        for row in 0..3 {
+            values[row * 5..row * 5 + 3].copy_from_slice(&m[row * 3..row * 3 + 3]);
+        }
+        ColorMatrix { values }
+    }
+}
+
+/// Apply `matrix` to every pixel; results are rounded and clamped to 0..255.
+pub fn color_matrix(img: &mut RgbaImageMut, matrix: &ColorMatrix) {
+    let mut profiler = Profiler::start("color_matrix");
+    let m = &matrix.values;
+    for y in 0..img.height() as usize {
+        for px in img.row_mut(y).chunks_exact_mut(4) {
+            let c = [px[0] as f32, px[1] as f32, px[2] as f32, px[3] as f32];
+            for (row, out) in px.iter_mut().enumerate() {
+                let r = &m[row * 5..row * 5 + 5];
+                let v = r[0] * c[0] + r[1] * c[1] + r[2] * c[2] + r[3] * c[3] + r[4] * 255.0;
+                *out = v.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    profiler.stage("pixels");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::sepia::sepia;
+    use crate::filters::test_util::lcg;
+
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut seed = seed;
+        (0..len).map(|_| (lcg(&mut seed) % 256) as u8).collect()
+    }
+
+    fn apply(data: &[u8], matrix: &ColorMatrix) -> Vec<u8> {
+        let mut out = data.to_vec();
+        color_matrix(&mut RgbaImageMut::new(&mut out, 8, 8).unwrap(), matrix);
+        out
+    }
+
+    #[test]
+    fn presets_give_known_values() {
+        let red = [255, 0, 0, 200];
+        assert_eq!(apply(&[red; 64].concat(), &ColorMatrix::grayscale())[..4], [54, 54, 54, 200]);
+        assert_eq!(apply(&[red; 64].concat(), &ColorMatrix::invert())[..4], [0, 255, 255, 200]);
+        assert_eq!(apply(&[red; 64].concat(), &ColorMatrix::sepia())[..4], [100, 89, 69, 200]);
+        let desaturated = ColorMatrix::saturate(0.0).unwrap();
+        assert_eq!(desaturated.values()[..3], desaturated.values()[5..8]);
+        assert_eq!(ColorMatrix::saturate(1.0).unwrap(), ColorMatrix::IDENTITY);
+    }
+
+    #[test]
+    fn invert_twice_is_identity() {
+        let data = noise(64 * 4, 23);
+        let once = apply(&data, &ColorMatrix::invert());
+        assert_ne!(once, data);
+        assert_eq!(apply(&once, &ColorMatrix::invert()), data);
+    }
+
+    #[test]
+    fn intensity_blends_with_identity() {
+        let data = noise(64 * 4, 5);
+        for matrix in [ColorMatrix::sepia(), ColorMatrix::grayscale(), ColorMatrix::invert()] {
+            assert_eq!(apply(&data, &matrix.with_intensity(0.0).unwrap()), data);
+            assert_eq!(apply(&data, &matrix.with_intensity(1.0).unwrap()), apply(&data, &matrix));
+        }
+        for bad in [-0.1, 1.5, f32::NAN] {
+            assert_eq!(
+                ColorMatrix::sepia().with_intensity(bad),
+                Err(ImageError::InvalidParameter { name: "intensity" })
+            );
+        }
+    }
+
+    #[test]
+    fn sepia_matrix_matches_sepia_filter() {
+        let data = noise(64 * 4, 11);
+        let mut expected = data.clone();
+        sepia(&mut RgbaImageMut::new(&mut expected, 8, 8).unwrap());
+        let got = apply(&data, &ColorMatrix::sepia());
+        // The filter truncates, the matrix rounds
+        for (&g, &e) in got.iter().zip(&expected) {
+            assert!(g == e || g == e + 1, "{} vs {}", g, e);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use crate::error::ImageError;
+
+/// 8-neighbourhood, edge neighbours first so a chain prefers straight steps over diagonal shortcuts.
+const NEIGHBOURS: [(isize, isize); 8] = [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+/// Ordered point chains traced from a one-pixel-wide edge mask.
+///
+/// Points are stored flat as `x0, y0, x1, y1, ...` in pixel units, at pixel centres
+/// (`x + 0.5`) unless sub-pixel refinement moved them onto the edge, see
+/// [`CannyConfig::subpixel`](crate::filters::edge_canny::CannyConfig::subpixel). Chain `i`
+/// covers points `offsets[i]..offsets[i + 1]`, so `offsets` has one entry more than there
+/// are chains. A closed chain repeats its first point at the end.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contours {
+    width: u32,
+    height: u32,
+    points: Vec<f32>,
+    offsets: Vec<u32>,
+}
+
+impl Contours {
+    fn new(width: u32, height: u32) -> Contours {
+        Contours { width, height, points: Vec::new(), offsets: vec![0] }
+    }
+
+    /// Size of the traced image.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Move every point, e.g. to refine pixel centres to sub-pixel positions.
+    pub(crate) fn map_points(&mut self, mut f: impl FnMut(f32, f32) -> (f32, f32)) {
+        for p in self.points.chunks_exact_mut(2) {
+            let (x, y) = f(p[0], p[1]);
+            p[0] = x;
+            p[1] = y;
+        }
+    }
+
+    fn push(&mut self, chain: &[(f32, f32)]) {
+        for &(x, y) in chain {
+            self.points.push(x);
+            self.points.push(y);
+        }
+        self.offsets.push((self.points.len() / 2) as u32);
+    }
+
+    /// Number of chains.
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All points, `x, y` interleaved.
+    pub fn points(&self) -> &[f32] {
+        &self.points
+    }
+
+    /// Start point index of every chain, followed by the total point count.
+    pub fn offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    /// Interleaved `x, y` coordinates of chain `i`.
+    pub fn chain(&self, i: usize) -> &[f32] {
+        &self.points[(self.offsets[i] as usize) * 2..(self.offsets[i + 1] as usize) * 2]
+    }
+
+    /// Chains one by one, each as interleaved `x, y` coordinates.
+    pub fn iter(&self) -> impl Iterator<Item = &[f32]> + '_ {
+        (0..self.len()).map(move |i| self.chain(i))
+    }
+
+    /// Douglas-Peucker simplification of every chain: points closer than `epsilon` pixels
+    /// to the simplified line are dropped. Chain ends are always kept.
+    pub fn simplify(&self, epsilon: f32) -> Result<Contours, ImageError> {
+        if !epsilon.is_finite() || epsilon < 0.0 {
+            return Err(ImageError::InvalidParameter { name: "epsilon" });
+        }
+        let mut out = Contours::new(self.width, self.height);
+        let mut chain: Vec<(f32, f32)> = Vec::new();
+        for flat in self.iter() {
+            chain.clear();
+            chain.extend(flat.chunks_exact(2).map(|p| (p[0], p[1])));
+            out.push(&douglas_peucker(&chain, epsilon));
+        }
+        Ok(out)
+    }
+}
+
+/// Trace a thin `w x h` edge mask (e.g. Canny hysteresis output) into ordered 8-connected chains.
+///
+/// Chains start at end points first, so open curves come out end to end; whatever remains
+/// afterwards lies on loops, which are closed. At junctions one branch continues the chain
+/// and the others become chains of their own. Isolated pixels are dropped.
+pub fn trace_contours(mask: &[bool], w: usize, h: usize) -> Contours {
+    let mut visited = vec![false; w * h];
+    let mut contours = Contours::new(w as u32, h as u32);
+
+    let neighbours = |i: usize| {
+        let x = (i % w) as isize;
+        let y = (i / w) as isize;
+        NEIGHBOURS.iter().filter_map(move |&(dx, dy)| {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= w as isize || ny >= h as isize {
+                return None;
+            }
+            let j = (ny as usize) * w + (nx as usize);
+            if mask[j] { Some(j) } else { None }
+        })
+    };
+
+    // Follow unvisited neighbours from `start` until the chain runs out
+    let walk = |start: usize, visited: &mut [bool], chain: &mut Vec<usize>| {
+        let mut cur = start;
+        while let Some(next) = neighbours(cur).find(|&j| !visited[j]) {
+            visited[next] = true;
+            chain.push(next);
+            cur = next;
+        }
+    };
+
+    let mut chain: Vec<usize> = Vec::new();
+    let mut points: Vec<(f32, f32)> = Vec::new();
+    for pass in 0..2 {
+        for start in 0..w * h {
+            if !mask[start] || visited[start] {
+                continue;
+            }
+            // First pass: only end points (a single neighbour)
+            if pass == 0 && neighbours(start).count() != 1 {
+                continue;
+            }
+            visited[start] = true;
+            chain.clear();
+            chain.push(start);
+            walk(start, &mut visited, &mut chain);
+
+            if pass == 1 {
+                // Started mid-curve: extend backwards from the start as well
+                let mut back = Vec::new();
+                walk(start, &mut visited, &mut back);
+                if !back.is_empty() {
+                    back.reverse();
+                    back.extend_from_slice(&chain);
+                    chain = back;
+                }
+                // Close loops whose ends touch
+                let (first, last) = (chain[0], chain[chain.len() - 1]);
+                if chain.len() > 2 && neighbours(last).any(|j| j == first) {
+                    chain.push(first);
+                }
+            }
+
+            if chain.len() < 2 {
+                continue;
+            }
+            points.clear();
+            points.extend(chain.iter().map(|&i| (((i % w) as f32) + 0.5, ((i / w) as f32) + 0.5)));
+            contours.push(&points);
+        }
+    }
+    contours
+}
+
+/// Zhang-Suen thinning: erode a `w x h` mask (e.g. a thick Sobel or Canny edge map) down to
+/// one-pixel-wide, 8-connected skeleton lines that [`trace_contours`] can follow.
+pub fn thin_mask(mask: &[bool], w: usize, h: usize) -> Vec<bool> {
+    let mut cur = mask.to_vec();
+    let at = |m: &[bool], x: usize, y: usize, dx: isize, dy: isize| -> bool {
+        let (nx, ny) = ((x as isize) + dx, (y as isize) + dy);
+        nx >= 0 && ny >= 0 && nx < w as isize && ny < h as isize && m[(ny as usize) * w + (nx as usize)]
+    };
+    let mut remove: Vec<usize> = Vec::new();
+    loop {
+        let mut changed = false;
+        for step in 0..2 {
+            remove.clear();
+            for y in 0..h {
+                for x in 0..w {
+                    if !cur[y * w + x] {
+                        continue;
+                    }
+                    // P2..P9 clockwise from north
+                    let p = [
+                        at(&cur, x, y, 0, -1),
+                        at(&cur, x, y, 1, -1),
+                        at(&cur, x, y, 1, 0),
+                        at(&cur, x, y, 1, 1),
+                        at(&cur, x, y, 0, 1),
+                        at(&cur, x, y, -1, 1),
+                        at(&cur, x, y, -1, 0),
+                        at(&cur, x, y, -1, -1),
+                    ];
+                    let neighbours = p.iter().filter(|&&v| v).count();
+                    let transitions = (0..8).filter(|&i| !p[i] && p[(i + 1) % 8]).count();
+                    let (north, east, south, west) = (p[0], p[2], p[4], p[6]);
+                    let deletable = if step == 0 {
+                        !(east && south && (north || west))
+                    } else {
+                        !(north && west && (east || south))
+                    };
+                    if (2..=6).contains(&neighbours) && transitions == 1 && deletable {
+                        remove.push(y * w + x);
+                    }
+                }
+            }
+            for &i in &remove {
+                cur[i] = false;
+            }
+            changed |= !remove.is_empty();
+        }
+        if !changed {
+            return cur;
+        }
+    }
+}
+
+/// Iterative Douglas-Peucker on one chain.
+fn douglas_peucker(chain: &[(f32, f32)], epsilon: f32) -> Vec<(f32, f32)> {
+    if chain.len() < 3 {
+        return chain.to_vec();
+    }
+    let mut keep = vec![false; chain.len()];
+    keep[0] = true;
+    keep[chain.len() - 1] = true;
+
+    let mut stack = vec![(0usize, chain.len() - 1)];
+    while let Some((a, b)) = stack.pop() {
+        let mut worst = 0.0f32;
+        let mut worst_index = a;
+        for i in a + 1..b {
+            let d = distance_to_segment(chain[i], chain[a], chain[b]);
+            if d > worst {
+                worst = d;
+                worst_index = i;
+            }
+        }
+        if worst > epsilon {
+            keep[worst_index] = true;
+            stack.push((a, worst_index));
+            stack.push((worst_index, b));
+        }
+    }
+
+    chain
+        .iter()
+        .zip(keep)
+        .filter_map(|(&p, k)| if k { Some(p) } else { None })
+        .collect()
+}
+
+/// Distance from `p` to the segment `a..b`; closed chains have `a == b`.
+fn distance_to_segment(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 == 0.0 { 0.0 } else { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0) };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `w x h` mask from rows of `#` (set) and `.` (clear).
+    fn mask(rows: &[&str]) -> (Vec<bool>, usize, usize) {
+        let m = rows.iter().flat_map(|r| r.chars().map(|c| c == '#')).collect();
+        (m, rows[0].len(), rows.len())
+    }
+
+    #[test]
+    fn square_ring_is_one_closed_chain() {
+        let (m, w, h) = mask(&["......", ".####.", ".#..#.", ".#..#.", ".####.", "......"]);
+        let contours = trace_contours(&m, w, h);
+        assert_eq!(contours.len(), 1);
+        let chain = contours.chain(0);
+        // 12 ring pixels plus the repeated start point
+        assert_eq!(chain.len(), 2 * 13);
+        assert_eq!(chain[..2], chain[chain.len() - 2..]);
+        for p in chain.chunks_exact(2) {
+            let (x, y) = ((p[0] - 0.5) as usize, (p[1] - 0.5) as usize);
+            assert!(m[y * w + x], "({}, {}) is not on the ring", x, y);
+        }
+    }
+
+    #[test]
+    fn open_line_runs_end_to_end_and_isolated_pixels_are_dropped() {
+        let (m, w, h) = mask(&["#.....", "......", ".####.", "......"]);
+        let contours = trace_contours(&m, w, h);
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours.chain(0), &[1.5, 2.5, 2.5, 2.5, 3.5, 2.5, 4.5, 2.5]);
+    }
+
+    #[test]
+    fn thinning_reduces_a_thick_bar_to_one_pixel() {
+        let (m, w, h) = mask(&["..........", ".########.", ".########.", ".########.", ".........."]);
+        let thin = thin_mask(&m, w, h);
+        assert!(thin.iter().any(|&v| v));
+        for x in 0..w {
+            assert!((0..h).filter(|&y| thin[y * w + x]).count() <= 1, "column {} is thicker than one pixel", x);
+        }
+    }
+
+    #[test]
+    fn douglas_peucker_respects_epsilon() {
+        let chain = [(0.0, 0.0), (1.0, 0.1), (2.0, 0.4), (3.0, 0.1), (4.0, 0.0)];
+        assert_eq!(douglas_peucker(&chain, 0.5), vec![(0.0, 0.0), (4.0, 0.0)]);
+        assert_eq!(douglas_peucker(&chain, 0.3), vec![(0.0, 0.0), (2.0, 0.4), (4.0, 0.0)]);
+        assert_eq!(douglas_peucker(&chain, 0.0).len(), chain.len());
+
+        let (m, w, h) = mask(&["......", ".####.", "......"]);
+        let simplified = trace_contours(&m, w, h).simplify(0.1).unwrap();
+        assert_eq!(simplified.chain(0), &[1.5, 1.5, 4.5, 1.5]);
+        assert!(simplified.simplify(-1.0).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::error::ImageError;
+use crate::filters::lut::{lut_pixels, Lut};
+use crate::image::RgbaImageMut;
+use crate::profile::Profiler;
+
+/// Control points `(input, output)` in 0..=255, per channel and for the master (RGB) curve.
+/// An empty list leaves its channel unchanged. As in Photoshop Curves, each channel goes
+/// through its own curve first and then through `master`; alpha is kept.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Curves {
+    pub master: Vec<(f32, f32)>,
+    pub red: Vec<(f32, f32)>,
+    pub green: Vec<(f32, f32)>,
+    pub blue: Vec<(f32, f32)>,
+}
+
+impl Curves {
+    /// The combined lookup table of all four curves.
+    pub fn lut(&self) -> Result<Lut, ImageError> {
+        let (channels, master) = self.splines()?;
+        Ok(channels.then(&master))
+    }
+
+    /// Sampled per-channel curves and master curve, not yet combined.
+    fn splines(&self) -> Result<(Lut, Lut), ImageError> {
+        let identity = *Lut::identity().table(0);
+        let table = |points: &[(f32, f32)], name: &'static str| -> Result<[u8; 256], ImageError> {
+            if points.is_empty() { Ok(identity) } else { curve_table(points, name) }
+        };
+        let channels = Lut::new(
+            table(&self.red, "red")?,
+            table(&self.green, "green")?,
+            table(&self.blue, "blue")?,
+            identity
+        );
+        Ok((channels, Lut::rgb(table(&self.master, "master")?)))
+    }
+}
+
+pub fn curves(img: &mut RgbaImageMut, curves: &Curves) -> Result<(), ImageError> {
+    let mut profiler = Profiler::start("curves");
+    let (channels, master) = curves.splines()?;
+    profiler.stage("spline");
+    let lut = channels.then(&master);
+    profiler.stage("build_lut");
+    lut_pixels(img, &lut);
+    profiler.stage("pixels");
+    Ok(())
+}
+
+/// Sample the monotone cubic (Fritsch-Carlson) spline through `points` at every input level.
+/// The curve never overshoots between points, and is flat beyond the first and last point.
+/// Fails with `InvalidParameter(name)` for fewer than two points, values outside 0..=255,
+/// or two points with the same input.
+pub fn curve_table(points: &[(f32, f32)], name: &'static str) -> Result<[u8; 256], ImageError> {
+    let in_range = |v: f32| (0.0..=255.0).contains(&v);
+    if points.len() < 2 || !points.iter().all(|&(x, y)| in_range(x) && in_range(y)) {
+        return Err(ImageError::InvalidParameter { name });
+    }
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if points.windows(2).any(|p| p[0].0 == p[1].0) {
+        return Err(ImageError::InvalidParameter { name });
+    }
+
+    let tangents = monotone_tangents(&points);
+    let mut table = [0u8; 256];
+    let mut k = 0;
+    for (i, out) in table.iter_mut().enumerate() {
+        let x = i as f32;
+        let y = if x <= points[0].0 {
+            points[0].1
+        } else if x >= points[points.len() - 1].0 {
+            points[points.len() - 1].1
+        } else {
+            while x > points[k + 1].0 {
+                k += 1;
+            }
+            let ((x0, y0), (x1, y1)) = (points[k], points[k + 1]);
+            let h = x1 - x0;
+            let t = (x - x0) / h;
+            // Cubic Hermite basis
+            let (t2, t3) = (t * t, t * t * t);
+            (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+                + (t3 - 2.0 * t2 + t) * h * tangents[k]
+                + (-2.0 * t3 + 3.0 * t2) * y1
+                + (t3 - t2) * h * tangents[k + 1]
+        };
+        *out = y.round().clamp(0.0, 255.0) as u8;
+    }
+    Ok(table)
+}
+
+/// Fritsch-Carlson tangents for points sorted by strictly increasing input.
+fn monotone_tangents(points: &[(f32, f32)]) -> Vec<f32> {
+    let n = points.len();
+    let slopes: Vec<f32> = points.windows(2).map(|p| (p[1].1 - p[0].1) / (p[1].0 - p[0].0)).collect();
+
+    let mut m = vec![0.0f32; n];
+    m[0] = slopes[0];
+    m[n - 1] = slopes[n - 2];
+    for k in 1..n - 1 {
+        // Flat at local extrema, otherwise the mean of the neighbouring slopes
+        m[k] = if slopes[k - 1] * slopes[k] <= 0.0 { 0.0 } else { 0.5 * (slopes[k - 1] + slopes[k]) };
+    }
+    for (k, &d) in slopes.iter().enumerate() {
+        if d == 0.0 {
+            m[k] = 0.0;
+            m[k + 1] = 0.0;
+            continue;
+        }
+        // Keep (alpha, beta) inside the circle of radius 3 so the segment stays monotone
+        let (a, b) = (m[k] / d, m[k + 1] / d);
+        let r = a * a + b * b;
+        if r > 9.0 {
+            let t = 3.0 / r.sqrt();
+            m[k] = t * a * d;
+            m[k + 1] = t * b * d;
+        }
+    }
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagonal_points_give_identity() {
+        let table = curve_table(&[(0.0, 0.0), (64.0, 64.0), (255.0, 255.0)], "master").unwrap();
+        assert_eq!(&table, Lut::identity().table(0));
+    }
+
+    #[test]
+    fn monotone_points_do_not_overshoot() {
+        let points = [(0.0, 0.0), (40.0, 10.0), (60.0, 200.0), (200.0, 210.0), (255.0, 255.0)];
+        let table = curve_table(&points, "master").unwrap();
+        assert!(table.windows(2).all(|w| w[0] <= w[1]));
+        assert!(table[40..=60].iter().all(|&v| (10..=200).contains(&v)));
+        assert!(table[60..=200].iter().all(|&v| (200..=210).contains(&v)));
+    }
+
+    #[test]
+    fn ends_are_held_flat() {
+        let table = curve_table(&[(100.0, 30.0), (50.0, 20.0)], "red").unwrap();
+        assert_eq!(table[0], 20);
+        assert_eq!(table[255], 30);
+        assert!(curve_table(&[(50.0, 0.0), (50.0, 9.0)], "red").is_err());
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn curves_profile_spline_and_table_stages() {
+        let mut data = [10u8, 20, 30, 40];
+        let curve = Curves { master: vec![(0.0, 20.0), (255.0, 235.0)], ..Curves::default() };
+        curves(&mut RgbaImageMut::new(&mut data, 1, 1).unwrap(), &curve).unwrap();
+        let profile = crate::profile::last_profile().unwrap();
+        assert_eq!(profile.filter, "curves");
+        let stages: Vec<_> = profile.stages.iter().map(|s| s.name).collect();
+        assert_eq!(stages, ["spline", "build_lut", "pixels"]);
+
+        let rejected = Curves { red: vec![(0.0, 0.0)], ..Curves::default() };
+        assert!(curves(&mut RgbaImageMut::new(&mut data, 1, 1).unwrap(), &rejected).is_err());
+        assert_eq!(crate::profile::last_profile(), Some(profile));
+    }
+}
@@ -1,203 +1,614 @@
-use wasm_bindgen::prelude::*;
+use std::str::FromStr;
 
-/// Backward-compatible default: equivalent to JS `edgeDetectionCannySimple(..., { strength: 'medium' })`
-#[wasm_bindgen]
-pub fn edge_detection_canny(ptr: *mut u8, width: u32, height: u32) {
-    edge_detection_canny_strength(ptr, width, height, "medium".to_string());
+use crate::error::ImageError;
+use crate::filters::border::BorderMode;
+use crate::filters::contour::{trace_contours, Contours};
+use crate::filters::gradient::{pixel_planes, GradientMode};
+use crate::filters::percentile::percentile_threshold_f32;
+use crate::filters::threshold::otsu_level;
+use crate::image::{GrayImageMut, RgbaImage, RgbaImageMut};
+use crate::profile::Profiler;
+
+/// Canny presets; a higher strength detects more (and finer) edges.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CannyStrength {
+    /// 7-tap blur, 90th/75th percentile thresholds.
+    Low = 0,
+    /// 5-tap blur, 85th/70th percentile thresholds.
+    Medium = 1,
+    /// 3-tap blur, 80th/65th percentile thresholds.
+    High = 2,
 }
 
-/// Config wrapper kept for compatibility. We map the legacy `sigma` to the closest preset:
-/// sigma >= 1.8 -> 'low', sigma < 1.0 -> 'high', otherwise 'medium'.
-/// Note: thresholds now follow the simplified method (fractions of max NMS), ignoring `high_percentile/low_ratio`.
-#[wasm_bindgen]
-pub fn edge_detection_canny_cfg(
-    ptr: *mut u8,
-    width: u32,
-    height: u32,
-    _high_percentile: f32,
-    _low_ratio: f32,
-    sigma: f32
-) {
-    let strength = if sigma >= 1.8 { "low" } else if sigma < 1.0 { "high" } else { "medium" };
-    edge_detection_canny_strength(ptr, width, height, strength.to_string());
+impl FromStr for CannyStrength {
+    type Err = ImageError;
+
+    /// Case-insensitive "low", "medium" or "high".
+    fn from_str(s: &str) -> Result<CannyStrength, ImageError> {
+        match s.to_ascii_lowercase().as_str() {
+            "low" => Ok(CannyStrength::Low),
+            "medium" => Ok(CannyStrength::Medium),
+            "high" => Ok(CannyStrength::High),
+            _ => Err(ImageError::InvalidParameter { name: "strength" }),
+        }
+    }
 }
 
-/// New simple API: strength in {"low","medium","high"} — mirrors JS
-#[wasm_bindgen]
-pub fn edge_detection_canny_strength(ptr: *mut u8, width: u32, height: u32, strength: String) {
-    let len = (width * height * 4) as usize;
-    let mem = unsafe { std::slice::from_raw_parts_mut(ptr, len) };
+/// Thickness of the Canny output edges.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stroke {
+    /// One-pixel edges as found by NMS.
+    Thin = 0,
+    /// Dilated by a 3x3 square.
+    Medium = 1,
+    /// Dilated by a 5x5 square.
+    Thick = 2,
+}
 
-    let w = width as usize;
-    let h = height as usize;
-    if w < 3 || h < 3 {
-        return;
+impl Stroke {
+    /// Dilation radius of the output edges.
+    pub fn radius(self) -> usize {
+        match self {
+            Stroke::Thin => 0,
+            Stroke::Medium => 1,
+            Stroke::Thick => 2,
+        }
     }
+}
 
-    // --- 1) RGBA -> grayscale (0..255 as f32)
-    let mut gray: Vec<f32> = vec![0.0; w * h];
-    for y in 0..h {
-        for x in 0..w {
-            let i = (y * w + x) * 4;
-            let r = mem[i] as i32;
-            let g = mem[i + 1] as i32;
-            let b = mem[i + 2] as i32;
-            gray[y * w + x] = ((77 * r + 150 * g + 29 * b) >> 8) as f32;
+impl FromStr for Stroke {
+    type Err = ImageError;
+
+    /// Case-insensitive "thin", "medium" or "thick".
+    fn from_str(s: &str) -> Result<Stroke, ImageError> {
+        match s.to_ascii_lowercase().as_str() {
+            "thin" => Ok(Stroke::Thin),
+            "medium" => Ok(Stroke::Medium),
+            "thick" => Ok(Stroke::Thick),
+            _ => Err(ImageError::InvalidParameter { name: "stroke" }),
         }
     }
+}
 
-    // --- 2) Gaussian blur (separable) with preset kernel
-    let (k, norm, high_percentile, low_percentile) = preset_for_strength(&strength);
-    let blurred = gaussian_blur_separable_with_kernel(&gray, w, h, k, norm);
+/// Non-maximum suppression variant.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CannyNms {
+    /// Compare against the two neighbours in the nearest of four directions (original behaviour).
+    Quantized = 0,
+    /// Compare against magnitudes interpolated one pixel away along the exact gradient
+    /// direction, which gives smoother diagonal edges.
+    Interpolated = 1,
+}
 
-    // --- 3) Sobel -> magnitude & 4-dir quantized orientation
-    let (mag, dir) = sobel_mag_dir(&blurred, w, h);
+/// Pre-smoothing applied to the grayscale image before the gradient.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CannyBlur {
+    /// Sampled Gaussian of the given sigma, radius `ceil(3 * sigma)`.
+    Gaussian(f32),
+    /// Pascal-triangle kernel with an odd number of taps (3 = [1 2 1]/4), as used by the JS presets.
+    Binomial(usize),
+    None,
+}
 
-    // --- 4) Non-Maximum Suppression (classic)
-    let nms = non_maximum_suppression(&mag, &dir, w, h);
+/// How a hysteresis threshold is derived from the non-maximum-suppressed magnitudes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CannyThreshold {
+    /// Percentile (0..=1) of the non-zero NMS magnitudes.
+    Percentile(f32),
+    /// Fraction (0..=1) of the strongest NMS magnitude.
+    FractionOfMax(f32),
+    /// Fraction of the high threshold. Only valid for the low threshold.
+    RatioOfHigh(f32),
+    /// Gradient magnitude in grayscale units.
+    Absolute(f32),
+    /// Otsu level of the non-zero NMS magnitudes, binned into 256 levels up to the maximum.
+    Otsu,
+}
 
-    // --- 5) Thresholds using PERCENTILE (not max-based) + hysteresis
-    // CHANGE: Use percentile for robustness to outliers
-    let high = calculate_percentile_threshold_f32(&nms, high_percentile);
-    let low = calculate_percentile_threshold_f32(&nms, low_percentile);
+/// Smallest Gaussian pre-blur sigma. Below it the kernel is a single tap anyway, and for tiny
+/// values `2 * sigma^2` underflows and the tap becomes NaN; use [`CannyBlur::None`] instead.
+pub const MIN_SIGMA: f32 = 0.1;
 
-    let edges = double_threshold_and_hysteresis_abs(&nms, w, h, high, low);
+/// Largest Gaussian pre-blur sigma; the kernel has `2 * ceil(3 * sigma) + 1` taps.
+pub const MAX_SIGMA: f32 = 64.0;
 
-    // Default stroke to match JS default ('medium')
-    let mask = post_thicken_bool(&edges, w, h, "medium");
+/// Full Canny configuration. The strength presets are [`CannyConfig::preset`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CannyConfig {
+    pub blur: CannyBlur,
+    pub high: CannyThreshold,
+    pub low: CannyThreshold,
+    /// Thickness of the final edges.
+    pub stroke: Stroke,
+    /// Border handling for blur, Sobel and NMS. `Skip` blurs with clamping and leaves the
+    /// one-pixel border without edges, which is the original behaviour.
+    pub border: BorderMode,
+    pub nms: CannyNms,
+    /// Luma or colour gradient, see [`GradientMode`].
+    pub gradient: GradientMode,
+    /// Refine point outputs ([`canny_edge_points`], [`canny_contours`]) to sub-pixel precision.
+    /// The binary edge image is unaffected.
+    pub subpixel: bool,
+}
+
+impl Default for CannyConfig {
+    fn default() -> CannyConfig {
+        CannyConfig::preset(CannyStrength::Medium)
+    }
+}
+
+impl CannyConfig {
+    /// Preset for `strength` with a medium stroke and skipped borders.
+    pub fn preset(strength: CannyStrength) -> CannyConfig {
+        // Mirrors JS kernelAndThresholdForStrength()
+        // CHANGE: Now returns percentile values instead of fractions
+        // This is more robust to outliers and works consistently across image sizes
+        let (taps, high_percentile, low_percentile) = match strength {
+            CannyStrength::Low => (7, 0.9, 0.75), // 7-tap Pascal
+            CannyStrength::Medium => (5, 0.85, 0.7), // 5-tap Pascal
+            CannyStrength::High => (3, 0.8, 0.65), // 3-tap
+        };
+        CannyConfig {
+            blur: CannyBlur::Binomial(taps),
+            high: CannyThreshold::Percentile(high_percentile),
+            low: CannyThreshold::Percentile(low_percentile),
+            // Default stroke to match JS default ('medium')
+            stroke: Stroke::Medium,
+            border: BorderMode::Skip,
+            nms: CannyNms::Quantized,
+            gradient: GradientMode::Luma,
+            subpixel: false,
+        }
+    }
+
+    fn validate(&self) -> Result<(), ImageError> {
+        match self.blur {
+            CannyBlur::Gaussian(sigma) if !(MIN_SIGMA..=MAX_SIGMA).contains(&sigma) => {
+                return Err(ImageError::InvalidParameter { name: "sigma" });
+            }
+            CannyBlur::Binomial(taps) if taps % 2 == 0 || taps > 63 => {
+                return Err(ImageError::InvalidParameter { name: "taps" });
+            }
+            _ => {}
+        }
+        for (name, spec) in [("high", self.high), ("low", self.low)] {
+            let ok = match spec {
+                CannyThreshold::Percentile(v) | CannyThreshold::FractionOfMax(v) => (0.0..=1.0).contains(&v),
+                CannyThreshold::RatioOfHigh(v) => name == "low" && v.is_finite() && v >= 0.0,
+                CannyThreshold::Absolute(v) => v.is_finite() && v >= 0.0,
+                CannyThreshold::Otsu => true,
+            };
+            if !ok {
+                return Err(ImageError::InvalidParameter { name });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Backward-compatible default: equivalent to JS `edgeDetectionCannySimple(..., { strength: 'medium' })`
+pub fn edge_detection_canny(img: &mut RgbaImageMut) -> Result<(), ImageError> {
+    edge_detection_canny_with(img, &CannyConfig::default())
+}
+
+/// Legacy parameter set: Gaussian of `sigma` (no blur when 0), high threshold at
+/// `high_percentile` of the NMS magnitudes and low threshold at `low_ratio * high`.
+pub fn edge_detection_canny_cfg(
+    img: &mut RgbaImageMut,
+    high_percentile: f32,
+    low_ratio: f32,
+    sigma: f32
+) -> Result<(), ImageError> {
+    let config = CannyConfig {
+        blur: if sigma == 0.0 { CannyBlur::None } else { CannyBlur::Gaussian(sigma) },
+        high: CannyThreshold::Percentile(high_percentile),
+        low: CannyThreshold::RatioOfHigh(low_ratio),
+        ..CannyConfig::default()
+    };
+    edge_detection_canny_with(img, &config)
+}
+
+/// New simple API: strength preset — mirrors JS
+pub fn edge_detection_canny_strength(
+    img: &mut RgbaImageMut,
+    strength: CannyStrength
+) -> Result<(), ImageError> {
+    edge_detection_canny_with(img, &CannyConfig::preset(strength))
+}
+
+/// New API with stroke control
+pub fn edge_detection_canny_strength_stroke(
+    img: &mut RgbaImageMut,
+    strength: CannyStrength,
+    stroke: Stroke
+) -> Result<(), ImageError> {
+    let config = CannyConfig { stroke, ..CannyConfig::preset(strength) };
+    edge_detection_canny_with(img, &config)
+}
+
+/// Canny with one [`BorderMode`] applied to the blur, Sobel and NMS stages alike.
+pub fn edge_detection_canny_border(
+    img: &mut RgbaImageMut,
+    strength: CannyStrength,
+    stroke: Stroke,
+    border: BorderMode
+) -> Result<(), ImageError> {
+    let config = CannyConfig { stroke, border, ..CannyConfig::preset(strength) };
+    edge_detection_canny_with(img, &config)
+}
+
+pub fn edge_detection_canny_with(img: &mut RgbaImageMut, config: &CannyConfig) -> Result<(), ImageError> {
+    check_input(img.as_image(), config)?;
+    let mut profiler = Profiler::start("edge_detection_canny");
+    let stages = run_stages(img.as_image(), config, &mut profiler);
+    let w = stages.w;
 
     // --- 6) Write back RGBA (binary)
-    for y in 0..h {
+    for y in 0..stages.h {
+        let row = img.row_mut(y);
         for x in 0..w {
-            let o = (y * w + x) * 4;
-            let v = if mask[y * w + x] { 255u8 } else { 0u8 };
-            mem[o] = v;
-            mem[o + 1] = v;
-            mem[o + 2] = v;
-            mem[o + 3] = 255u8;
+            let o = x * 4;
+            let v = if stages.mask[y * w + x] { 255u8 } else { 0u8 };
+            row[o] = v;
+            row[o + 1] = v;
+            row[o + 2] = v;
+            row[o + 3] = 255u8;
         }
     }
+    profiler.stage("write");
+    Ok(())
 }
 
-/// New API with stroke control: {"thin","medium","thick"}
-#[wasm_bindgen]
-pub fn edge_detection_canny_strength_stroke(
-    ptr: *mut u8,
+/// Like [`edge_detection_canny_with`], but writes the binary edge map (0 or 255) into the
+/// single-channel `dst` (same size as `src`) and leaves `src` untouched.
+pub fn edge_detection_canny_into(
+    src: RgbaImage,
+    dst: &mut GrayImageMut,
+    config: &CannyConfig
+) -> Result<(), ImageError> {
+    dst.require_size(src.width(), src.height())?;
+    check_input(src, config)?;
+    let mut profiler = Profiler::start("edge_detection_canny_into");
+    let stages = run_stages(src, config, &mut profiler);
+    let w = stages.w;
+
+    // --- 6) Write the mask (one byte per pixel)
+    for y in 0..stages.h {
+        for (out, &edge) in dst.row_mut(y).iter_mut().zip(&stages.mask[y * w..(y + 1) * w]) {
+            *out = if edge { 255 } else { 0 };
+        }
+    }
+    profiler.stage("write");
+    Ok(())
+}
+
+/// Intermediate result selectable from [`CannyDebug`]. Every stage is an opaque RGBA image.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CannyStage {
+    /// Luma of the input, or its RGB channels with a colour [`GradientMode`].
+    Gray = 0,
+    /// The same after the pre-blur.
+    Blurred = 1,
+    /// Sobel gradient magnitude, scaled so the strongest gradient is 255.
+    Magnitude = 2,
+    /// Quantized gradient direction, brightness following the magnitude:
+    /// red horizontal gradient (vertical edge), yellow 45°, green vertical gradient, blue 135°.
+    Direction = 3,
+    /// Magnitudes surviving non-maximum suppression, same scale as `Magnitude`.
+    Nms = 4,
+    /// White strong edges, green weak edges kept by hysteresis, dark red weak edges dropped.
+    Hysteresis = 5,
+    /// Final output, including the stroke.
+    Edges = 6,
+}
+
+/// Colours of [`CannyStage::Direction`], indexed by quantized direction.
+const DIRECTION_COLOURS: [[u8; 3]; 4] = [[255, 0, 0], [255, 255, 0], [0, 255, 0], [0, 128, 255]];
+
+/// Colours of [`CannyStage::Hysteresis`], indexed by `HYST_*` state.
+const HYSTERESIS_COLOURS: [[u8; 3]; 4] = [[0, 0, 0], [128, 0, 0], [255, 255, 255], [0, 255, 0]];
+
+/// Every stage of one Canny run, kept as planes and rendered for inspection on request.
+pub struct CannyDebug {
     width: u32,
     height: u32,
-    strength: String,
-    stroke: String
-) {
-    let len = (width * height * 4) as usize;
-    let mem = unsafe { std::slice::from_raw_parts_mut(ptr, len) };
+    stages: Stages,
+    /// Maps gradient magnitudes onto 0..=255
+    mag_scale: f32,
+}
 
-    let w = width as usize;
-    let h = height as usize;
-    if w < 3 || h < 3 {
-        return;
+impl CannyDebug {
+    pub fn width(&self) -> u32 {
+        self.width
     }
 
-    // 1) grayscale
-    let mut gray: Vec<f32> = vec![0.0; w * h];
-    for y in 0..h {
-        for x in 0..w {
-            let i = (y * w + x) * 4;
-            let r = mem[i] as i32;
-            let g = mem[i + 1] as i32;
-            let b = mem[i + 2] as i32;
-            gray[y * w + x] = ((77 * r + 150 * g + 29 * b) >> 8) as f32;
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Tightly packed `width * height * 4` RGBA pixels of `stage`, rendered on each call.
+    pub fn stage(&self, stage: CannyStage) -> Vec<u8> {
+        let s = &self.stages;
+        let n = s.w * s.h;
+        let mag_scale = self.mag_scale;
+        match stage {
+            CannyStage::Gray => render(n, |i| plane_colour(&s.planes, i)),
+            CannyStage::Blurred => render(n, |i| plane_colour(&s.blurred, i)),
+            CannyStage::Magnitude => render(n, |i| grey_level(s.mag[i] * mag_scale)),
+            CannyStage::Direction => render(n, |i| {
+                let k = s.mag[i] * mag_scale / 255.0;
+                DIRECTION_COLOURS[s.dir[i] as usize].map(|c| ((c as f32) * k).round() as u8)
+            }),
+            CannyStage::Nms => render(n, |i| grey_level(s.nms[i] * mag_scale)),
+            CannyStage::Hysteresis => render(n, |i| HYSTERESIS_COLOURS[s.hysteresis[i] as usize]),
+            CannyStage::Edges => render(n, |i| if s.mask[i] { [255; 3] } else { [0; 3] }),
         }
     }
+}
 
-    // 2) blur (preset)
-    let (k, norm, high_percentile, low_percentile) = preset_for_strength(&strength);
-    let blurred = gaussian_blur_separable_with_kernel(&gray, w, h, k, norm);
+/// Run Canny on `img` without modifying it and keep every intermediate stage.
+pub fn canny_debug(img: RgbaImage, config: &CannyConfig) -> Result<CannyDebug, ImageError> {
+    check_input(img, config)?;
+    let mut profiler = Profiler::start("canny_debug");
+    let stages = run_stages(img, config, &mut profiler);
+    let max_mag = stages.mag.iter().copied().fold(0.0f32, f32::max);
+    let mag_scale = if max_mag > 0.0 { 255.0 / max_mag } else { 0.0 };
+    Ok(CannyDebug { width: img.width(), height: img.height(), stages, mag_scale })
+}
+
+/// Canny edges of `img` as ordered point chains, see [`trace_contours`]. The chains follow
+/// the one-pixel hysteresis output; `config.stroke` does not apply.
+pub fn canny_contours(img: RgbaImage, config: &CannyConfig) -> Result<Contours, ImageError> {
+    check_input(img, config)?;
+    let mut profiler = Profiler::start("canny_contours");
+    let s = run_stages(img, config, &mut profiler);
+    let edges: Vec<bool> = s.hysteresis.iter().map(|&state| is_edge(state)).collect();
+    let mut contours = trace_contours(&edges, s.w, s.h);
+    profiler.stage("trace");
+    if config.subpixel {
+        // Chain points sit at pixel centres; shift each by its own refinement
+        contours.map_points(|x, y| {
+            let (dx, dy) = subpixel_offset(&s, x as usize, y as usize, config);
+            (x + dx, y + dy)
+        });
+        profiler.stage("subpixel");
+    }
+    Ok(contours)
+}
 
-    // 3) sobel
-    let (mag, dir) = sobel_mag_dir(&blurred, w, h);
+/// Location of every Canny edge pixel as interleaved `x, y`, in raster order. Pixel centres
+/// (`x + 0.5`) unless `config.subpixel` is set, in which case each point is moved along the
+/// gradient to the peak of a parabola fitted through the magnitude on both sides of it.
+pub fn canny_edge_points(img: RgbaImage, config: &CannyConfig) -> Result<Vec<f32>, ImageError> {
+    check_input(img, config)?;
+    let mut profiler = Profiler::start("canny_edge_points");
+    let s = run_stages(img, config, &mut profiler);
+    let mut points = Vec::new();
+    for y in 0..s.h {
+        for x in 0..s.w {
+            if !is_edge(s.hysteresis[y * s.w + x]) {
+                continue;
+            }
+            let (dx, dy) = if config.subpixel { subpixel_offset(&s, x, y, config) } else { (0.0, 0.0) };
+            points.push((x as f32) + 0.5 + dx);
+            points.push((y as f32) + 0.5 + dy);
+        }
+    }
+    profiler.stage("points");
+    Ok(points)
+}
 
-    // 4) nms
-    let nms = non_maximum_suppression(&mag, &dir, w, h);
+/// Intermediate planes of one Canny run, row-major `w x h`.
+struct Stages {
+    w: usize,
+    h: usize,
+    /// Luma, or R, G and B
+    planes: Vec<Vec<f32>>,
+    blurred: Vec<Vec<f32>>,
+    mag: Vec<f32>,
+    dir: Vec<u8>,
+    nms: Vec<f32>,
+    /// `HYST_*` state per pixel
+    hysteresis: Vec<u8>,
+    mask: Vec<bool>,
+}
 
-    // 5) thresholds (PERCENTILE-BASED) + hysteresis
-    let high = calculate_percentile_threshold_f32(&nms, high_percentile);
-    let low = calculate_percentile_threshold_f32(&nms, low_percentile);
-    let edges = double_threshold_and_hysteresis_abs(&nms, w, h, high, low);
+/// Reject what [`run_stages`] cannot process. Called before the profiler starts, so a
+/// rejected call leaves the previous profile in place.
+fn check_input(img: RgbaImage, config: &CannyConfig) -> Result<(), ImageError> {
+    img.require_min_size(3)?;
+    config.validate()
+}
 
-    // 5b) optional thickening
-    let mask = post_thicken_bool(&edges, w, h, &stroke);
+/// Every stage up to the edge mask, for input that passed [`check_input`].
+fn run_stages(img: RgbaImage, config: &CannyConfig, profiler: &mut Profiler) -> Stages {
+    let w = img.width() as usize;
+    let h = img.height() as usize;
 
-    // 6) write back
+    // --- 1) RGBA -> grayscale (0..255 as f32), or one plane per colour channel
+    let mut planes: Vec<Vec<f32>> = vec![vec![0.0; w * h]; config.gradient.channels()];
     for y in 0..h {
-        for x in 0..w {
-            let o = (y * w + x) * 4;
-            let v = if mask[y * w + x] { 255u8 } else { 0u8 };
-            mem[o] = v;
-            mem[o + 1] = v;
-            mem[o + 2] = v;
-            mem[o + 3] = 255u8;
+        for (x, px) in img.row(y).chunks_exact(4).enumerate() {
+            let values = pixel_planes(config.gradient, px);
+            for (plane, &v) in planes.iter_mut().zip(&values) {
+                plane[y * w + x] = v as f32;
+            }
         }
     }
-}
+    profiler.stage("grayscale");
 
-// ----------------- Helpers -----------------
+    // --- 2) Gaussian blur (separable)
+    let blurred: Vec<Vec<f32>> = match config.blur {
+        CannyBlur::Gaussian(sigma) => {
+            let k = gaussian_kernel_f32(sigma);
+            planes
+                .iter()
+                .map(|p| gaussian_blur_separable_with_kernel(p, w, h, &k, 1.0, config.border))
+                .collect()
+        }
+        CannyBlur::Binomial(taps) => {
+            let (k, norm) = binomial_kernel(taps);
+            planes
+                .iter()
+                .map(|p| gaussian_blur_separable_with_kernel(p, w, h, &k, norm, config.border))
+                .collect()
+        }
+        CannyBlur::None => planes.clone(),
+    };
+    profiler.stage("blur");
+
+    // --- 3) Sobel -> magnitude & 4-dir quantized orientation
+    let SobelPlanes { mag, dir, gx, gy } = sobel_mag_dir(&blurred, w, h, config.border, config.gradient);
+    profiler.stage("sobel");
 
-/// Calculate percentile-based threshold from an array of f32 values.
-/// This is robust to outliers and adaptive to image content.
-/// Identical algorithm to JavaScript implementation.
-fn calculate_percentile_threshold_f32(values: &[f32], percentile: f32) -> f32 {
-    // Filter out zero values (background/flat regions)
-    let mut non_zero: Vec<f32> = values
+    // --- 4) Non-Maximum Suppression (classic)
+    let nms = match config.nms {
+        CannyNms::Quantized => non_maximum_suppression(&mag, &dir, w, h, config.border),
+        CannyNms::Interpolated => non_maximum_suppression_interpolated(&mag, &gx, &gy, w, h, config.border),
+    };
+    // The gradient vectors are only needed by NMS
+    drop((gx, gy));
+    profiler.stage("nms");
+
+    // --- 5) Thresholds + hysteresis
+    let high = resolve_threshold(config.high, &nms, 0.0);
+    let low = resolve_threshold(config.low, &nms, high);
+    profiler.stage("thresholds");
+    let hysteresis = double_threshold_and_hysteresis_abs(&nms, w, h, high, low);
+    let edges: Vec<bool> = hysteresis
         .iter()
-        .copied()
-        .filter(|&v| v > 0.0)
+        .map(|&s| is_edge(s))
         .collect();
+    profiler.stage("hysteresis");
+
+    // 5b) optional thickening
+    let mask = if config.stroke != Stroke::Thin {
+        dilate_binary_bool(&edges, w, h, config.stroke.radius(), 1)
+    } else {
+        edges
+    };
+    profiler.stage("stroke");
 
-    if non_zero.is_empty() {
-        return 0.0; // All zeros, no edges
+    Stages { w, h, planes, blurred, mag, dir, nms, hysteresis, mask }
+}
+
+// ----------------- Helpers -----------------
+
+/// Sub-pixel shift of the edge at `(x, y)`: the magnitude is sampled one pixel either way along
+/// the true (unquantized) gradient direction and the vertex of the parabola through the three
+/// samples gives the offset, at most half a pixel.
+fn subpixel_offset(s: &Stages, x: usize, y: usize, config: &CannyConfig) -> (f32, f32) {
+    let border = config.border;
+    let (gx, gy) = gradient_at(&s.blurred, s.w, s.h, x, y, border, config.gradient);
+    let norm = (gx * gx + gy * gy).sqrt();
+    if norm == 0.0 {
+        return (0.0, 0.0);
     }
+    let (ux, uy) = (gx / norm, gy / norm);
+    let (fx, fy) = (x as f32, y as f32);
+    let before = bilinear(&s.mag, s.w, s.h, fx - ux, fy - uy, border);
+    let centre = s.mag[y * s.w + x];
+    let after = bilinear(&s.mag, s.w, s.h, fx + ux, fy + uy, border);
 
-    // Sort in ascending order (same as JavaScript)
-    non_zero.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let curvature = before - 2.0 * centre + after;
+    if curvature >= 0.0 {
+        return (0.0, 0.0); // not a peak
+    }
+    let t = (0.5 * (before - after) / curvature).clamp(-0.5, 0.5);
+    (t * ux, t * uy)
+}
 
-    // Calculate percentile index (floor to match JavaScript)
-    let index = ((non_zero.len() as f32) * percentile).floor() as usize;
+/// Bilinear sample of a `w x h` plane at fractional pixel coordinates, extrapolated per
+/// `border`; a constant border has no gradient, so it reads as 0.
+fn bilinear(plane: &[f32], w: usize, h: usize, x: f32, y: f32, border: BorderMode) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let at = |dx: isize, dy: isize| -> f32 {
+        match (border.index((x0 as isize) + dx, w), border.index((y0 as isize) + dy, h)) {
+            (Some(nx), Some(ny)) => plane[ny * w + nx],
+            _ => 0.0,
+        }
+    };
+    let top = at(0, 0) * (1.0 - tx) + at(1, 0) * tx;
+    let bottom = at(0, 1) * (1.0 - tx) + at(1, 1) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
 
-    // Clamp index to valid range
-    let clamped_index = index.min(non_zero.len() - 1);
+/// Opaque RGBA image of `n` pixels whose colour comes from `colour(i)`.
+fn render(n: usize, colour: impl Fn(usize) -> [u8; 3]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(n * 4);
+    for i in 0..n {
+        let [r, g, b] = colour(i);
+        out.extend_from_slice(&[r, g, b, 255]);
+    }
+    out
+}
 
-    non_zero[clamped_index]
+#[inline]
+fn grey_level(v: f32) -> [u8; 3] {
+    [v.round().clamp(0.0, 255.0) as u8; 3]
 }
 
+/// Pixel `i` of a luma plane as grey, or of R, G, B planes as colour.
 #[inline]
-fn preset_for_strength(strength: &str) -> (&'static [f32], f32, f32, f32) {
-    // Mirrors JS kernelAndThresholdForStrength()
-    // CHANGE: Now returns percentile values instead of fractions
-    // This is more robust to outliers and works consistently across image sizes
-    match strength.to_ascii_lowercase().as_str() {
-        "low" =>
-            (
-                &[1.0, 6.0, 15.0, 20.0, 15.0, 6.0, 1.0], // 7-tap Pascal
-                64.0,
-                0.9, // highPercentile
-                0.75, // lowPercentile
-            ),
-        "high" =>
-            (
-                &[1.0, 2.0, 1.0], // 3-tap
-                4.0,
-                0.8, // highPercentile
-                0.65, // lowPercentile
-            ),
-        _ =>
-            (
-                // "medium"
-                &[1.0, 4.0, 6.0, 4.0, 1.0], // 5-tap Pascal
-                16.0,
-                0.85, // highPercentile
-                0.7, // lowPercentile
-            ),
+fn plane_colour(planes: &[Vec<f32>], i: usize) -> [u8; 3] {
+    match planes {
+        [luma] => grey_level(luma[i]),
+        _ => [0, 1, 2].map(|c| planes[c][i].round().clamp(0.0, 255.0) as u8),
+    }
+}
+
+/// Absolute threshold value for `spec`. `high` is only used by `RatioOfHigh`.
+fn resolve_threshold(spec: CannyThreshold, nms: &[f32], high: f32) -> f32 {
+    match spec {
+        // Percentile (not max-based) for robustness to outliers
+        CannyThreshold::Percentile(p) => percentile_threshold_f32(nms, p),
+        CannyThreshold::FractionOfMax(f) => nms.iter().copied().fold(0.0f32, f32::max) * f,
+        CannyThreshold::RatioOfHigh(r) => high * r,
+        CannyThreshold::Absolute(v) => v,
+        CannyThreshold::Otsu => otsu_threshold(nms),
+    }
+}
+
+/// Smallest magnitude whose 0..255 bin lies above the Otsu level of the non-zero magnitudes.
+fn otsu_threshold(nms: &[f32]) -> f32 {
+    let max = nms.iter().copied().fold(0.0f32, f32::max);
+    if max == 0.0 {
+        return 0.0;
+    }
+    let mut hist = [0u32; 256];
+    for &v in nms.iter().filter(|&&v| v > 0.0) {
+        hist[((v / max) * 255.0).round() as usize] += 1;
+    }
+    ((otsu_level(&hist) as f32) + 0.5) * max / 255.0
+}
+
+/// Row `taps - 1` of Pascal's triangle and its sum, e.g. 5 -> ([1, 4, 6, 4, 1], 16).
+fn binomial_kernel(taps: usize) -> (Vec<f32>, f32) {
+    let mut k = vec![1.0f32; taps];
+    for n in 1..taps {
+        for i in (1..n).rev() {
+            k[i] += k[i - 1];
+        }
     }
+    (k, (1u64 << (taps - 1)) as f32)
+}
+
+/// Normalized sampled Gaussian, radius `ceil(3 * sigma)`.
+fn gaussian_kernel_f32(sigma: f32) -> Vec<f32> {
+    let r = (3.0 * sigma).ceil() as isize;
+    let mut k: Vec<f32> = (-r..=r)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = k.iter().sum();
+    for v in k.iter_mut() {
+        *v /= sum;
+    }
+    k
 }
 
 #[inline]
@@ -206,9 +617,11 @@ fn gaussian_blur_separable_with_kernel(
     w: usize,
     h: usize,
     k: &[f32],
-    norm: f32
+    norm: f32,
+    border: BorderMode
 ) -> Vec<f32> {
     let r: isize = ((k.len() as isize) - 1) / 2;
+    let c = border.constant() as f32;
     let mut tmp = vec![0.0f32; w * h];
     let mut dst = vec![0.0f32; w * h];
 
@@ -217,8 +630,11 @@ fn gaussian_blur_separable_with_kernel(
         for x in 0..w {
             let mut acc = 0.0f32;
             for i in -r..=r {
-                let xx = clamp_i32((x as i32) + (i as i32), 0, (w - 1) as i32) as usize;
-                acc += src[y * w + xx] * k[(i + r) as usize];
+                let v = match border.index((x as isize) + i, w) {
+                    Some(xx) => src[y * w + xx],
+                    None => c,
+                };
+                acc += v * k[(i + r) as usize];
             }
             tmp[y * w + x] = acc / norm;
         }
@@ -229,44 +645,100 @@ fn gaussian_blur_separable_with_kernel(
         for x in 0..w {
             let mut acc = 0.0f32;
             for i in -r..=r {
-                let yy = clamp_i32((y as i32) + (i as i32), 0, (h - 1) as i32) as usize;
-                acc += tmp[yy * w + x] * k[(i + r) as usize];
+                let v = match border.index((y as isize) + i, h) {
+                    Some(yy) => tmp[yy * w + x],
+                    None => c,
+                };
+                acc += v * k[(i + r) as usize];
             }
             dst[y * w + x] = acc / norm;
         }
     }
+
     dst
 }
 
+/// Pixel range an operator with a one-pixel reach covers: interior only for `Skip`.
+#[inline]
+fn gradient_range(n: usize, border: BorderMode) -> std::ops::Range<usize> {
+    if border == BorderMode::Skip { 1..n - 1 } else { 0..n }
+}
+
+/// 3x3 Sobel gradient `(gx, gy)` at `(x, y)`.
 #[inline]
-fn sobel_mag_dir(src: &[f32], w: usize, h: usize) -> (Vec<f32>, Vec<u8>) {
+fn sobel_at(src: &[f32], w: usize, h: usize, x: usize, y: usize, border: BorderMode) -> (f32, f32) {
     const GX: [i32; 9] = [-1, 0, 1, -2, 0, 2, -1, 0, 1];
     const GY: [i32; 9] = [-1, -2, -1, 0, 0, 0, 1, 2, 1];
+    let mut gx = 0.0f32;
+    let mut gy = 0.0f32;
+    let mut k = 0usize;
+    for ky in 0..3 {
+        for kx in 0..3 {
+            let ix = (x + kx) as isize - 1;
+            let iy = (y + ky) as isize - 1;
+            let v = border.sample_2d(src, w, h, ix, iy);
+            gx += v * (GX[k] as f32);
+            gy += v * (GY[k] as f32);
+            k += 1;
+        }
+    }
+    (gx, gy)
+}
+
+/// Combined gradient of all planes at `(x, y)`, see [`GradientMode::combine`].
+#[inline]
+fn gradient_at(
+    planes: &[Vec<f32>],
+    w: usize,
+    h: usize,
+    x: usize,
+    y: usize,
+    border: BorderMode,
+    mode: GradientMode
+) -> (f32, f32) {
+    let mut gx = [0.0f32; 3];
+    let mut gy = [0.0f32; 3];
+    for (c, plane) in planes.iter().enumerate() {
+        (gx[c], gy[c]) = sobel_at(plane, w, h, x, y, border);
+    }
+    mode.combine(&gx[..planes.len()], &gy[..planes.len()])
+}
+
+/// Output of [`sobel_mag_dir`], row-major `w x h`.
+struct SobelPlanes {
+    mag: Vec<f32>,
+    /// Quantized direction 0..=3
+    dir: Vec<u8>,
+    /// Combined gradient vector, kept for interpolated NMS
+    gx: Vec<f32>,
+    gy: Vec<f32>,
+}
+
+#[inline]
+fn sobel_mag_dir(
+    src: &[Vec<f32>],
+    w: usize,
+    h: usize,
+    border: BorderMode,
+    mode: GradientMode
+) -> SobelPlanes {
     let mut mag = vec![0.0f32; w * h];
     let mut dir = vec![0u8; w * h];
-    for y in 1..h - 1 {
-        for x in 1..w - 1 {
-            let mut gx = 0.0f32;
-            let mut gy = 0.0f32;
-            let mut k = 0usize;
-            for ky in 0..3 {
-                for kx in 0..3 {
-                    let ix = x + kx - 1;
-                    let iy = y + ky - 1;
-                    let v = src[iy * w + ix];
-                    gx += v * (GX[k] as f32);
-                    gy += v * (GY[k] as f32);
-                    k += 1;
-                }
-            }
+    let mut gx_plane = vec![0.0f32; w * h];
+    let mut gy_plane = vec![0.0f32; w * h];
+    for y in gradient_range(h, border) {
+        for x in gradient_range(w, border) {
+            let (gx, gy) = gradient_at(src, w, h, x, y, border, mode);
             let m = (gx * gx + gy * gy).sqrt();
             mag[y * w + x] = m;
+            gx_plane[y * w + x] = gx;
+            gy_plane[y * w + x] = gy;
 
             let mut angle = gy.atan2(gx).to_degrees();
             if angle < 0.0 {
                 angle += 180.0;
             }
-            let q = if angle < 22.5 || angle >= 157.5 {
+            let q = if !(22.5..157.5).contains(&angle) {
                 0u8
             } else if angle < 67.5 {
                 1u8
@@ -278,20 +750,33 @@ fn sobel_mag_dir(src: &[f32], w: usize, h: usize) -> (Vec<f32>, Vec<u8>) {
             dir[y * w + x] = q;
         }
     }
-    (mag, dir)
+    SobelPlanes { mag, dir, gx: gx_plane, gy: gy_plane }
 }
 
 #[inline]
-fn non_maximum_suppression(mag: &[f32], dir: &[u8], w: usize, h: usize) -> Vec<f32> {
+fn non_maximum_suppression(
+    mag: &[f32],
+    dir: &[u8],
+    w: usize,
+    h: usize,
+    border: BorderMode
+) -> Vec<f32> {
     let mut out = vec![0.0f32; w * h];
-    for y in 1..h - 1 {
-        for x in 1..w - 1 {
+    // Magnitude outside the image: extrapolated like the image, except a constant border has no gradient
+    let at = |x: usize, y: usize, dx: isize, dy: isize| -> f32 {
+        match (border.index((x as isize) + dx, w), border.index((y as isize) + dy, h)) {
+            (Some(nx), Some(ny)) => mag[ny * w + nx],
+            _ => 0.0,
+        }
+    };
+    for y in gradient_range(h, border) {
+        for x in gradient_range(w, border) {
             let m = mag[y * w + x];
             let (m1, m2) = match dir[y * w + x] {
-                0 => (mag[y * w + x - 1], mag[y * w + x + 1]),
-                1 => (mag[(y - 1) * w + x + 1], mag[(y + 1) * w + x - 1]),
-                2 => (mag[(y - 1) * w + x], mag[(y + 1) * w + x]),
-                _ => (mag[(y - 1) * w + x - 1], mag[(y + 1) * w + x + 1]),
+                0 => (at(x, y, -1, 0), at(x, y, 1, 0)),
+                1 => (at(x, y, 1, -1), at(x, y, -1, 1)),
+                2 => (at(x, y, 0, -1), at(x, y, 0, 1)),
+                _ => (at(x, y, -1, -1), at(x, y, 1, 1)),
             };
             out[y * w + x] = if m >= m1 && m >= m2 { m } else { 0.0 };
         }
@@ -299,6 +784,45 @@ fn non_maximum_suppression(mag: &[f32], dir: &[u8], w: usize, h: usize) -> Vec<f
     out
 }
 
+// Hysteresis states: below low, weak not connected to a strong edge, strong, weak kept
+const HYST_NONE: u8 = 0;
+const HYST_WEAK: u8 = 1;
+const HYST_STRONG: u8 = 2;
+const HYST_PROMOTED: u8 = 3;
+
+#[inline]
+fn is_edge(state: u8) -> bool {
+    state == HYST_STRONG || state == HYST_PROMOTED
+}
+
+/// NMS along the exact gradient direction: the neighbours are bilinear samples one pixel away.
+fn non_maximum_suppression_interpolated(
+    mag: &[f32],
+    gx: &[f32],
+    gy: &[f32],
+    w: usize,
+    h: usize,
+    border: BorderMode
+) -> Vec<f32> {
+    let mut out = vec![0.0f32; w * h];
+    for y in gradient_range(h, border) {
+        for x in gradient_range(w, border) {
+            let m = mag[y * w + x];
+            if m == 0.0 {
+                continue;
+            }
+            let (ux, uy) = (gx[y * w + x] / m, gy[y * w + x] / m);
+            let (fx, fy) = (x as f32, y as f32);
+            let m1 = bilinear(mag, w, h, fx + ux, fy + uy, border);
+            let m2 = bilinear(mag, w, h, fx - ux, fy - uy, border);
+            if m >= m1 && m >= m2 {
+                out[y * w + x] = m;
+            }
+        }
+    }
+    out
+}
+
 #[inline]
 fn double_threshold_and_hysteresis_abs(
     nms: &[f32],
@@ -306,21 +830,18 @@ fn double_threshold_and_hysteresis_abs(
     h: usize,
     high: f32,
     low: f32
-) -> Vec<bool> {
-    let mut state = vec![0u8; w * h]; // 0 none, 1 weak, 2 strong
+) -> Vec<u8> {
+    let mut state = vec![HYST_NONE; w * h];
     for i in 0..w * h {
         let v = nms[i];
-        state[i] = if v >= high { 2 } else if v >= low { 1 } else { 0 };
+        state[i] = if v >= high { HYST_STRONG } else if v >= low { HYST_WEAK } else { HYST_NONE };
     }
 
     // Seed strong
     let mut stack: Vec<usize> = Vec::new();
-    for y in 1..h - 1 {
-        for x in 1..w - 1 {
-            let i = y * w + x;
-            if state[i] == 2 {
-                stack.push(i);
-            }
+    for (i, &s) in state.iter().enumerate() {
+        if s == HYST_STRONG {
+            stack.push(i);
         }
     }
 
@@ -334,8 +855,8 @@ fn double_threshold_and_hysteresis_abs(
                     continue;
                 }
                 let j = ny * w + nx;
-                if state[j] == 1 {
-                    state[j] = 2;
+                if state[j] == HYST_WEAK {
+                    state[j] = HYST_PROMOTED;
                     stack.push(j);
                 }
             }
@@ -343,21 +864,10 @@ fn double_threshold_and_hysteresis_abs(
     }
 
     state
-        .into_iter()
-        .map(|s| s == 2)
-        .collect()
-}
-
-fn post_thicken_bool(src: &Vec<bool>, w: usize, h: usize, stroke: &str) -> Vec<bool> {
-    match stroke.to_ascii_lowercase().as_str() {
-        "medium" => dilate_binary_bool(src, w, h, 1, 1),
-        "thick" => dilate_binary_bool(src, w, h, 2, 1),
-        _ => src.clone(), // thin
-    }
 }
 
-fn dilate_binary_bool(src: &Vec<bool>, w: usize, h: usize, r: usize, iters: usize) -> Vec<bool> {
-    let mut cur = src.clone();
+fn dilate_binary_bool(src: &[bool], w: usize, h: usize, r: usize, iters: usize) -> Vec<bool> {
+    let mut cur = src.to_vec();
     let mut out = vec![false; w * h];
     for _ in 0..iters {
         for y in 0..h {
@@ -383,7 +893,255 @@ fn dilate_binary_bool(src: &Vec<bool>, w: usize, h: usize, r: usize, iters: usiz
     cur
 }
 
-#[inline]
-fn clamp_i32(v: i32, lo: i32, hi: i32) -> i32 {
-    v.max(lo).min(hi)
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::GrayImageMut;
+
+    /// Opaque `w x h` image whose gray level is `level(x, y)`.
+    fn gray_image(w: usize, h: usize, level: impl Fn(usize, usize) -> u8) -> Vec<u8> {
+        let mut data = Vec::with_capacity(w * h * 4);
+        for y in 0..h {
+            for x in 0..w {
+                let v = level(x, y);
+                data.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+        data
+    }
+
+    fn edge_mask(data: &[u8], w: usize, h: usize, config: &CannyConfig) -> Vec<u8> {
+        let mut out = vec![0u8; w * h];
+        let src = RgbaImage::new(data, w as u32, h as u32).unwrap();
+        edge_detection_canny_into(src, &mut GrayImageMut::new(&mut out, w as u32, h as u32).unwrap(), config).unwrap();
+        out
+    }
+
+    fn unblurred(border: BorderMode) -> CannyConfig {
+        CannyConfig {
+            blur: CannyBlur::None,
+            high: CannyThreshold::Absolute(100.0),
+            low: CannyThreshold::Absolute(50.0),
+            stroke: Stroke::Thin,
+            border,
+            ..CannyConfig::default()
+        }
+    }
+
+    #[test]
+    fn border_modes_extrapolate_the_edge_column() {
+        // Horizontal ramp, 40 levels per column: 10, 50, ..., 210
+        let (w, h) = (6, 4);
+        let plane = vec![(0..w * h).map(|i| (10 + 40 * (i % w)) as f32).collect::<Vec<f32>>()];
+        let expected = [
+            (BorderMode::Clamp, 160.0),
+            (BorderMode::Reflect, 160.0),
+            (BorderMode::Reflect101, 0.0),
+            (BorderMode::Wrap, 640.0),
+            (BorderMode::Constant(0), 200.0),
+            (BorderMode::Skip, 0.0),
+        ];
+        for (border, mag) in expected {
+            let m = sobel_mag_dir(&plane, w, h, border, GradientMode::Luma).mag;
+            assert_eq!(m[w], mag, "{:?}", border);
+        }
+    }
+
+    #[test]
+    fn border_edges_per_mode() {
+        let (w, h) = (8, 8);
+        let flat = gray_image(w, h, |_, _| 100);
+        let on_border = |x: usize, y: usize| x == 0 || y == 0 || x == w - 1 || y == h - 1;
+
+        for border in [BorderMode::Clamp, BorderMode::Reflect, BorderMode::Reflect101, BorderMode::Wrap, BorderMode::Skip] {
+            assert!(edge_mask(&flat, w, h, &unblurred(border)).iter().all(|&v| v == 0), "{:?}", border);
+        }
+        assert!(edge_mask(&flat, w, h, &unblurred(BorderMode::Constant(100))).iter().all(|&v| v == 0));
+
+        // A black frame around a flat image: every border pixel is an edge, nothing inside
+        let framed = edge_mask(&flat, w, h, &unblurred(BorderMode::Constant(0)));
+        for y in 0..h {
+            for x in 0..w {
+                assert_eq!(framed[y * w + x] == 255, on_border(x, y), "({}, {})", x, y);
+            }
+        }
+
+        // Dark left half: Wrap also sees the step between the last and the first column
+        let halves = gray_image(w, h, |x, _| if x < w / 2 { 0 } else { 200 });
+        let clamp = edge_mask(&halves, w, h, &unblurred(BorderMode::Clamp));
+        let wrap = edge_mask(&halves, w, h, &unblurred(BorderMode::Wrap));
+        let skip = edge_mask(&halves, w, h, &unblurred(BorderMode::Skip));
+        assert!((0..h).all(|y| clamp[y * w] == 0 && clamp[y * w + w - 1] == 0));
+        assert!((0..h).any(|y| wrap[y * w] == 255 || wrap[y * w + w - 1] == 255));
+        assert!((0..w).all(|x| skip[x] == 0 && skip[(h - 1) * w + x] == 0));
+        assert!((1..h - 1).any(|y| skip[y * w + w / 2 - 1] == 255 || skip[y * w + w / 2] == 255));
+    }
+
+    #[test]
+    fn debug_stages_are_rendered_in_their_colours() {
+        // Strong step 0 -> 200 at x = 4 (gradient 800), weak step 200 -> 215 at x = 9 (gradient 60)
+        let (w, h) = (12, 5);
+        let data = gray_image(w, h, |x, _| match x { 0..=3 => 0, 4..=8 => 200, _ => 215 });
+        let config = unblurred(BorderMode::Reflect101);
+        let debug = canny_debug(RgbaImage::new(&data, w as u32, h as u32).unwrap(), &config).unwrap();
+        // Every row is the same; return the RGB of row 2
+        let row = |stage: CannyStage| -> Vec<[u8; 3]> {
+            let px = debug.stage(stage);
+            assert_eq!(px.len(), w * h * 4);
+            px[2 * w * 4..3 * w * 4].chunks_exact(4).map(|p| [p[0], p[1], p[2]]).collect()
+        };
+
+        assert_eq!(row(CannyStage::Gray)[3..5], [[0; 3], [200; 3]]);
+        // 60 / 800 * 255 = 19.1
+        let magnitude: Vec<u8> = row(CannyStage::Magnitude).iter().map(|c| c[0]).collect();
+        assert_eq!(magnitude, [0, 0, 0, 255, 255, 0, 0, 0, 19, 19, 0, 0]);
+        // Horizontal gradients are red, scaled by the magnitude
+        let direction = row(CannyStage::Direction);
+        assert_eq!((direction[3], direction[8], direction[0]), ([255, 0, 0], [19, 0, 0], [0, 0, 0]));
+
+        let nms = row(CannyStage::Nms);
+        let hysteresis = row(CannyStage::Hysteresis);
+        let edges = row(CannyStage::Edges);
+        for x in 0..w {
+            let expected = match (x, nms[x][0]) {
+                (_, 0) => HYSTERESIS_COLOURS[HYST_NONE as usize],
+                (3 | 4, 255) => HYSTERESIS_COLOURS[HYST_STRONG as usize],
+                // The weak step is too far from the strong one to be kept
+                (8 | 9, 19) => HYSTERESIS_COLOURS[HYST_WEAK as usize],
+                other => panic!("unexpected NMS output {:?}", other),
+            };
+            assert_eq!(hysteresis[x], expected, "x = {}", x);
+            assert_eq!(edges[x], if expected == [255; 3] { [255; 3] } else { [0; 3] }, "x = {}", x);
+        }
+        assert!(nms[3..5].contains(&[255; 3]) && nms[8..10].contains(&[19; 3]));
+
+        let mask = edge_mask(&data, w, h, &config);
+        let rendered: Vec<u8> = debug.stage(CannyStage::Edges).chunks_exact(4).map(|p| p[0]).collect();
+        assert_eq!(rendered, mask);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn profile_lists_every_stage_and_survives_rejected_calls() {
+        let (w, h) = (8, 6);
+        let data = gray_image(w, h, |x, _| if x < 4 { 0 } else { 200 });
+        let config = unblurred(BorderMode::Reflect101);
+        edge_mask(&data, w, h, &CannyConfig { blur: CannyBlur::Gaussian(1.0), ..config });
+        let profile = crate::profile::last_profile().unwrap();
+        assert_eq!(profile.filter, "edge_detection_canny_into");
+        let stages: Vec<_> = profile.stages.iter().map(|s| s.name).collect();
+        assert_eq!(stages, ["grayscale", "blur", "sobel", "nms", "thresholds", "hysteresis", "stroke", "write"]);
+        assert!(profile.stages.iter().all(|s| s.ms >= 0.0));
+
+        let mut out = vec![0u8; w * h];
+        let invalid = CannyConfig { blur: CannyBlur::Gaussian(-1.0), ..config };
+        let src = RgbaImage::new(&data, w as u32, h as u32).unwrap();
+        assert!(edge_detection_canny_into(src, &mut GrayImageMut::new(&mut out, w as u32, h as u32).unwrap(), &invalid).is_err());
+        assert_eq!(crate::profile::last_profile(), Some(profile));
+    }
+
+    #[test]
+    fn sigma_is_bounded() {
+        let data = gray_image(4, 4, |x, _| (x * 60) as u8);
+        let run = |config: CannyConfig| {
+            let mut out = [0u8; 16];
+            let src = RgbaImage::new(&data, 4, 4).unwrap();
+            edge_detection_canny_into(src, &mut GrayImageMut::new(&mut out, 4, 4).unwrap(), &config).err()
+        };
+        let sigma = |sigma: f32| CannyConfig { blur: CannyBlur::Gaussian(sigma), ..CannyConfig::default() };
+        assert_eq!(run(sigma(MAX_SIGMA)), None);
+        assert_eq!(run(sigma(MAX_SIGMA * 2.0)), Some(ImageError::InvalidParameter { name: "sigma" }));
+        assert_eq!(run(sigma(f32::INFINITY)), Some(ImageError::InvalidParameter { name: "sigma" }));
+        assert_eq!(run(sigma(MIN_SIGMA)), None);
+        for tiny in [MIN_SIGMA / 2.0, 1e-30, 0.0, f32::NAN] {
+            assert_eq!(run(sigma(tiny)), Some(ImageError::InvalidParameter { name: "sigma" }));
+        }
+
+        // Used to give NaN taps: every pixel an edge, and NaN sub-pixel coordinates
+        let step = gray_image(4, 3, |x, _| if x < 2 { 0 } else { 200 });
+        let config = CannyConfig { subpixel: true, ..sigma(1e-30) };
+        let points = canny_edge_points(RgbaImage::new(&step, 4, 3).unwrap(), &config);
+        assert_eq!(points.err(), Some(ImageError::InvalidParameter { name: "sigma" }));
+    }
+
+    #[test]
+    fn subpixel_points_follow_an_oblique_edge() {
+        // Anti-aliased edge along x cos 30 + y sin 30 = 12.3, dark on the near side
+        let (w, h) = (32, 32);
+        let (sin, cos) = 30f32.to_radians().sin_cos();
+        let distance = |x: f32, y: f32| x * cos + y * sin - 12.3;
+        let data = gray_image(w, h, |x, y| {
+            (255.0 * (0.5 + distance(x as f32 + 0.5, y as f32 + 0.5)).clamp(0.0, 1.0)).round() as u8
+        });
+        let img = RgbaImage::new(&data, w as u32, h as u32).unwrap();
+        let config = CannyConfig {
+            blur: CannyBlur::Gaussian(1.0),
+            high: CannyThreshold::Absolute(60.0),
+            low: CannyThreshold::Absolute(20.0),
+            ..unblurred(BorderMode::Reflect101)
+        };
+
+        let centres = canny_edge_points(img, &config).unwrap();
+        let refined = canny_edge_points(img, &CannyConfig { subpixel: true, ..config }).unwrap();
+        assert_eq!(centres.len(), refined.len());
+        assert!(centres.len() >= 2 * 10, "only {} points", centres.len() / 2);
+        assert_ne!(centres, refined);
+
+        // Mean distance to the true edge, away from the image border
+        let error = |points: &[f32]| {
+            let inner: Vec<f32> = points
+                .chunks_exact(2)
+                .filter(|p| p[0] > 3.0 && p[1] > 3.0 && p[0] < (w as f32) - 3.0 && p[1] < (h as f32) - 3.0)
+                .map(|p| distance(p[0], p[1]).abs())
+                .collect();
+            inner.iter().sum::<f32>() / (inner.len() as f32)
+        };
+        assert!(error(&refined) < 0.5 * error(&centres), "{} vs {}", error(&refined), error(&centres));
+    }
+
+    #[test]
+    fn interpolated_nms_matches_quantized_on_axis_aligned_steps() {
+        let (w, h) = (12, 10);
+        let config = CannyConfig { blur: CannyBlur::Gaussian(1.0), ..unblurred(BorderMode::Reflect101) };
+        let interpolated = CannyConfig { nms: CannyNms::Interpolated, ..config };
+        for data in [
+            gray_image(w, h, |x, _| if x < 5 { 20 } else { 220 }),
+            gray_image(w, h, |_, y| if y < 4 { 220 } else { 20 }),
+        ] {
+            let img = RgbaImage::new(&data, w as u32, h as u32).unwrap();
+            let quantized = canny_debug(img, &config).unwrap().stage(CannyStage::Nms);
+            assert!(quantized.iter().any(|&v| v > 0));
+            assert_eq!(canny_debug(img, &interpolated).unwrap().stage(CannyStage::Nms), quantized);
+        }
+    }
+
+    #[test]
+    fn interpolated_nms_keeps_only_the_ridge_of_an_oblique_edge() {
+        // Magnitude falls off linearly with the distance `d` from a ridge at 30 degrees
+        let (w, h) = (9, 9);
+        let (sin, cos) = 30f32.to_radians().sin_cos();
+        let d = |x: usize, y: usize| (x as f32 - 4.0) * cos + (y as f32 - 4.0) * sin - 0.2;
+        let mag: Vec<f32> = (0..w * h).map(|i| 4.0 - d(i % w, i / w).abs()).collect();
+        let gx: Vec<f32> = mag.iter().map(|m| m * cos).collect();
+        let gy: Vec<f32> = mag.iter().map(|m| m * sin).collect();
+        let nms = non_maximum_suppression_interpolated(&mag, &gx, &gy, w, h, BorderMode::Reflect101);
+
+        // Away from the border, where the ridge is reflected. Bilinear samples of the tent
+        // come out slightly low, so pixels just past half a pixel may survive too.
+        let mut suppressed = 0;
+        for y in 2..h - 2 {
+            for x in 2..w - 2 {
+                let (kept, d) = (nms[y * w + x] > 0.0, d(x, y).abs());
+                if d < 0.5 {
+                    assert!(kept, "ridge pixel ({}, {}) at d = {} was suppressed", x, y, d);
+                } else if d > 0.6 {
+                    assert!(!kept, "off-ridge pixel ({}, {}) at d = {} was kept", x, y, d);
+                    suppressed += usize::from(d < 1.0);
+                }
+            }
+        }
+        // Including direct neighbours of the ridge
+        assert!(suppressed >= 3);
+    }
 }
@@ -1,114 +1,295 @@
-use wasm_bindgen::prelude::*;
-
-/// Calculate percentile-based threshold from an array of values.
-/// This is robust to outliers and adaptive to image content.
-///
-/// Algorithm matches JavaScript implementation exactly:
-/// 1. Filter out zero values
-/// 2. Sort in ascending order
-/// 3. Return value at floor(length * percentile)
-fn calculate_percentile_threshold(values: &[i32], percentile: f32) -> i32 {
-    // Filter out zero values (background/flat regions)
-    let mut non_zero: Vec<i32> = values
-        .iter()
-        .copied()
-        .filter(|&v| v > 0)
-        .collect();
-
-    if non_zero.is_empty() {
-        return 0; // All zeros, no edges
-    }
+use crate::error::ImageError;
+use crate::filters::border::BorderMode;
+use crate::filters::gradient::{pixel_planes, GradientMode};
+use crate::filters::percentile::percentile_threshold_i32;
+use crate::filters::threshold::otsu_level;
+use crate::image::{GrayImageMut, RgbaImage, RgbaImageMut};
+use crate::profile::Profiler;
+
+/// ADAPTIVE THRESHOLD: Use 85th percentile of gradient magnitudes
+/// This is robust to outliers and works well for images of any size
+pub const DEFAULT_PERCENTILE: f32 = 0.85;
+
+/// What Sobel writes into the image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SobelMode {
+    /// Edge where the magnitude reaches the given percentile (0..=1) of non-zero magnitudes.
+    Percentile(f32),
+    /// Edge where the gradient magnitude is at least this value (0 ..= ~1442 for 8-bit input).
+    Absolute(f32),
+    /// Edge above the Otsu level of the normalized magnitude histogram.
+    Otsu,
+    /// No threshold: gradient magnitude scaled so the strongest edge is 255.
+    Magnitude,
+}
 
-    // Sort in ascending order (same as JavaScript)
-    non_zero.sort_unstable();
+/// Original behaviour: the one-pixel border is skipped and left transparent black.
+pub fn edge_detection_sobel(img: &mut RgbaImageMut) -> Result<(), ImageError> {
+    edge_detection_sobel_with_border(img, BorderMode::Skip)
+}
 
-    // Calculate percentile index (floor to match JavaScript)
-    let index = ((non_zero.len() as f32) * percentile).floor() as usize;
+/// Sobel with the given [`BorderMode`]. With anything but `Skip` the border pixels get
+/// a gradient too and take part in the percentile threshold.
+pub fn edge_detection_sobel_with_border(
+    img: &mut RgbaImageMut,
+    border: BorderMode
+) -> Result<(), ImageError> {
+    edge_detection_sobel_with(img, SobelMode::Percentile(DEFAULT_PERCENTILE), border, GradientMode::Luma)
+}
 
-    // Clamp index to valid range
-    let clamped_index = index.min(non_zero.len() - 1);
+/// Sobel with a selectable [`SobelMode`], [`BorderMode`] and [`GradientMode`].
+pub fn edge_detection_sobel_with(
+    img: &mut RgbaImageMut,
+    mode: SobelMode,
+    border: BorderMode,
+    gradient: GradientMode
+) -> Result<(), ImageError> {
+    check_input(img.as_image(), mode)?;
+    let mut profiler = Profiler::start("edge_detection_sobel");
+    let edges = sobel_edges(img.as_image(), mode, border, gradient, &mut profiler);
 
-    non_zero[clamped_index]
+    // Write the edge map; skipped border pixels become transparent black
+    let w = img.width() as usize;
+    let h = img.height() as usize;
+    let skip = border == BorderMode::Skip;
+    for y in 0..h {
+        let row = img.row_mut(y);
+        for (x, px) in row.chunks_exact_mut(4).enumerate() {
+            let edge = edges[y * w + x];
+            let inside = !skip || (x > 0 && x < w - 1 && y > 0 && y < h - 1);
+            px.copy_from_slice(&[edge, edge, edge, if inside { 255 } else { 0 }]);
+        }
+    }
+    profiler.stage("write");
+    Ok(())
 }
 
-#[wasm_bindgen]
-pub fn edge_detection_sobel(ptr: *mut u8, width: u32, height: u32) {
-    let len = (width * height * 4) as usize;
-    let mem = unsafe { std::slice::from_raw_parts_mut(ptr, len) };
+/// Like [`edge_detection_sobel_with`], but writes the edge map into the single-channel `dst`
+/// (same size as `src`) and leaves `src` untouched. Skipped border pixels are 0.
+pub fn edge_detection_sobel_into(
+    src: RgbaImage,
+    dst: &mut GrayImageMut,
+    mode: SobelMode,
+    border: BorderMode,
+    gradient: GradientMode
+) -> Result<(), ImageError> {
+    dst.require_size(src.width(), src.height())?;
+    check_input(src, mode)?;
+    let mut profiler = Profiler::start("edge_detection_sobel_into");
+    let edges = sobel_edges(src, mode, border, gradient, &mut profiler);
+
+    let w = src.width() as usize;
+    for y in 0..src.height() as usize {
+        dst.row_mut(y).copy_from_slice(&edges[y * w..(y + 1) * w]);
+    }
+    profiler.stage("write");
+    Ok(())
+}
 
+/// Reject input [`sobel_edges`] cannot process. Called before the profiler starts, so a
+/// rejected call leaves the previous profile in place.
+fn check_input(img: RgbaImage, mode: SobelMode) -> Result<(), ImageError> {
+    // 3x3 kernel needs at least one interior pixel
+    img.require_min_size(3)?;
+    match mode {
+        SobelMode::Percentile(p) if !(0.0..=1.0).contains(&p) => {
+            Err(ImageError::InvalidParameter { name: "percentile" })
+        }
+        SobelMode::Absolute(t) if !t.is_finite() || t < 0.0 => {
+            Err(ImageError::InvalidParameter { name: "threshold" })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Edge value (binary unless `Magnitude`) of every pixel, `w * h` bytes, for input that passed
+/// [`check_input`]. Pixels outside the processed area (the border under `Skip`) are 0.
+fn sobel_edges(
+    img: RgbaImage,
+    mode: SobelMode,
+    border: BorderMode,
+    gradient: GradientMode,
+    profiler: &mut Profiler
+) -> Vec<u8> {
     // Integer Sobel kernels (3x3)
     const GX: [i32; 9] = [-1, 0, 1, -2, 0, 2, -1, 0, 1];
     const GY: [i32; 9] = [-1, -2, -1, 0, 0, 0, 1, 2, 1];
 
-    // ADAPTIVE THRESHOLD: Use 85th percentile of gradient magnitudes
-    // This is robust to outliers and works well for images of any size
-    const PERCENTILE: f32 = 0.85;
+    let w = img.width() as usize;
+    let h = img.height() as usize;
 
-    let mut output = vec![0u8; len];
+    // Integer planes: luma (77*R + 150*G + 29*B) >> 8, or R, G and B
+    let channels = gradient.channels();
+    let mut planes: Vec<Vec<i32>> = vec![Vec::with_capacity(w * h); channels];
+    for y in 0..h {
+        for px in img.row(y).chunks_exact(4) {
+            let values = pixel_planes(gradient, px);
+            for (plane, &v) in planes.iter_mut().zip(&values) {
+                plane.push(v);
+            }
+        }
+    }
+    profiler.stage("grayscale");
+
+    // A constant border colour (c, c, c) has luma c
+    let border_gray = border.constant() as i32;
 
-    let w = width as usize;
-    let h = height as usize;
+    // Skip processes interior pixels only; borders remain 0
+    let (x0, x1, y0, y1) = if border == BorderMode::Skip { (1, w - 1, 1, h - 1) } else { (0, w, 0, h) };
 
     // Step 1: Compute all gradient magnitudes (squared to avoid sqrt)
-    let mut magnitudes: Vec<i32> = Vec::with_capacity((w - 2) * (h - 2));
+    let mut magnitudes: Vec<i32> = Vec::with_capacity((x1 - x0) * (y1 - y0));
 
-    // Process interior pixels; borders remain 0
-    for y in 1..h - 1 {
-        for x in 1..w - 1 {
-            let mut gx_sum: i32 = 0;
-            let mut gy_sum: i32 = 0;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let mut gx_sum = [0i32; 3];
+            let mut gy_sum = [0i32; 3];
 
             // Convolution window 3x3
             let mut k = 0usize;
             for ky in 0..3 {
                 for kx in 0..3 {
-                    let ix = x + kx - 1;
-                    let iy = y + ky - 1;
-                    let idx = (iy * w + ix) * 4;
-
-                    // Integer grayscale: approx 0.299R + 0.587G + 0.114B
-                    // Using (77*R + 150*G + 29*B) >> 8
-                    let r = mem[idx] as i32;
-                    let g = mem[idx + 1] as i32;
-                    let b = mem[idx + 2] as i32;
-                    let gray = ((77 * r + 150 * g + 29 * b) >> 8) as i32;
-
-                    gx_sum += gray * GX[k];
-                    gy_sum += gray * GY[k];
+                    let ix = border.index((x + kx) as isize - 1, w);
+                    let iy = border.index((y + ky) as isize - 1, h);
+                    for (c, plane) in planes.iter().enumerate() {
+                        let gray = match (ix, iy) {
+                            (Some(ix), Some(iy)) => plane[iy * w + ix],
+                            _ => border_gray,
+                        };
+
+                        gx_sum[c] += gray * GX[k];
+                        gy_sum[c] += gray * GY[k];
+                    }
                     k += 1;
                 }
             }
 
             // Squared gradient magnitude (avoid sqrt)
-            let mag2: i32 = gx_sum
-                .saturating_mul(gx_sum)
-                .saturating_add(gy_sum.saturating_mul(gy_sum));
+            let mag2: i32 = gradient.combine_sq(&gx_sum[..channels], &gy_sum[..channels]);
 
             magnitudes.push(mag2);
         }
     }
 
-    // Step 2: Calculate adaptive threshold using percentile
-    let threshold_sq = calculate_percentile_threshold(&magnitudes, PERCENTILE);
+    profiler.stage("gradient");
+
+    // Step 2: Map each squared magnitude to an output value
+    let max_mag = (magnitudes.iter().copied().max().unwrap_or(0) as f64).sqrt();
+    let normalized = |mag2: i32| -> u8 {
+        if max_mag == 0.0 { 0 } else { (((mag2 as f64).sqrt() / max_mag) * 255.0).round() as u8 }
+    };
+    let threshold_sq: f64 = match mode {
+        // Adaptive threshold using percentile
+        SobelMode::Percentile(p) => percentile_threshold_i32(&magnitudes, p) as f64,
+        SobelMode::Absolute(t) => (t as f64) * (t as f64),
+        SobelMode::Otsu | SobelMode::Magnitude => 0.0,
+    };
+    let otsu = if mode == SobelMode::Otsu {
+        let mut hist = [0u32; 256];
+        for &mag2 in &magnitudes {
+            hist[normalized(mag2) as usize] += 1;
+        }
+        otsu_level(&hist)
+    } else {
+        0
+    };
+
+    profiler.stage("threshold");
 
-    // Step 3: Apply threshold to create binary edge map
+    // Step 3: Edge value per pixel
+    let mut edges = vec![0u8; w * h];
     let mut mag_index = 0;
-    for y in 1..h - 1 {
-        for x in 1..w - 1 {
+    for y in y0..y1 {
+        for x in x0..x1 {
             let mag2 = magnitudes[mag_index];
             mag_index += 1;
 
-            // Binary edge map per paper's thresholding approach
-            let edge: u8 = if mag2 >= threshold_sq { 255 } else { 0 };
-
-            let out_idx = (y * w + x) * 4;
-            output[out_idx] = edge;
-            output[out_idx + 1] = edge;
-            output[out_idx + 2] = edge;
-            output[out_idx + 3] = 255;
+            edges[y * w + x] = match mode {
+                SobelMode::Percentile(_) | SobelMode::Absolute(_) => {
+                    if (mag2 as f64) >= threshold_sq { 255 } else { 0 }
+                }
+                SobelMode::Otsu => if normalized(mag2) > otsu { 255 } else { 0 },
+                SobelMode::Magnitude => normalized(mag2),
+            };
         }
     }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: usize = 9;
+    const H: usize = 5;
+
+    /// Vertical steps 0 -> 60 at x = 3 and 60 -> 200 at x = 6, so the gradient is 4 * 60 = 240
+    /// in columns 2 and 3 and 4 * 140 = 560 in columns 5 and 6.
+    fn steps() -> Vec<u8> {
+        (0..W * H)
+            .flat_map(|i| {
+                let v = match i % W { 0..=2 => 0, 3..=5 => 60, _ => 200 };
+                [v, v, v, 255]
+            })
+            .collect()
+    }
+
+    /// Output row of `mode`; every row is the same because the steps are vertical.
+    fn sobel_row(mode: SobelMode) -> Result<Vec<u8>, ImageError> {
+        let data = steps();
+        let mut out = vec![0u8; W * H];
+        edge_detection_sobel_into(
+            RgbaImage::new(&data, W as u32, H as u32)?,
+            &mut GrayImageMut::new(&mut out, W as u32, H as u32)?,
+            mode,
+            BorderMode::Clamp,
+            GradientMode::Luma
+        )?;
+        assert!(out.chunks_exact(W).all(|row| row == &out[..W]));
+        Ok(out[..W].to_vec())
+    }
+
+    fn edge_columns(mode: SobelMode) -> Vec<usize> {
+        let row = sobel_row(mode).unwrap();
+        assert!(row.iter().all(|&v| v == 0 || v == 255), "{:?} is not binary", mode);
+        (0..W).filter(|&x| row[x] == 255).collect()
+    }
 
-    // Copy result back to the original RGBA buffer
-    mem.copy_from_slice(&output);
+    #[test]
+    fn absolute_threshold_is_inclusive() {
+        assert_eq!(edge_columns(SobelMode::Absolute(240.0)), [2, 3, 5, 6]);
+        assert_eq!(edge_columns(SobelMode::Absolute(241.0)), [5, 6]);
+        assert_eq!(edge_columns(SobelMode::Absolute(560.0)), [5, 6]);
+        assert_eq!(edge_columns(SobelMode::Absolute(561.0)), [] as [usize; 0]);
+    }
+
+    #[test]
+    fn percentile_ranks_non_zero_magnitudes() {
+        assert_eq!(edge_columns(SobelMode::Percentile(0.0)), [2, 3, 5, 6]);
+        assert_eq!(edge_columns(SobelMode::Percentile(DEFAULT_PERCENTILE)), [5, 6]);
+        assert_eq!(edge_columns(SobelMode::Percentile(1.0)), [5, 6]);
+    }
+
+    #[test]
+    fn otsu_splits_the_magnitude_histogram() {
+        // Normalized levels 0 (5 columns), 109 (2) and 255 (2): splitting above 109 gives the
+        // larger between-class variance (8663 against 8179 for splitting above 0)
+        assert_eq!(edge_columns(SobelMode::Otsu), [5, 6]);
+    }
+
+    #[test]
+    fn magnitude_is_scaled_to_the_strongest_edge() {
+        // 240 / 560 * 255 = 109.3
+        assert_eq!(sobel_row(SobelMode::Magnitude).unwrap(), [0, 0, 109, 109, 0, 255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn out_of_range_thresholds_are_rejected() {
+        for mode in [SobelMode::Percentile(-0.1), SobelMode::Percentile(1.1), SobelMode::Percentile(f32::NAN)] {
+            assert_eq!(sobel_row(mode), Err(ImageError::InvalidParameter { name: "percentile" }));
+        }
+        for mode in [SobelMode::Absolute(-1.0), SobelMode::Absolute(f32::NAN), SobelMode::Absolute(f32::INFINITY)] {
+            assert_eq!(sobel_row(mode), Err(ImageError::InvalidParameter { name: "threshold" }));
+        }
+    }
 }
@@ -0,0 +1,135 @@
+/// Which signal the Sobel and Canny detectors differentiate.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientMode {
+    /// Gradient of the luma `(77R + 150G + 29B) >> 8` (original behaviour). Misses edges
+    /// between colours of equal brightness.
+    Luma = 0,
+    /// Per pixel, the gradient of whichever RGB channel changes the most.
+    MaxChannel = 1,
+    /// Di Zenzo: the dominant eigenvector of the RGB structure tensor, i.e. the direction in
+    /// which the colour changes fastest. Averaged over the channels, so a gray image gives
+    /// the same magnitude as `Luma` up to the luma rounding.
+    DiZenzo = 2,
+}
+
+impl GradientMode {
+    /// Number of planes the detector works on: 1 (luma) or 3 (R, G, B).
+    pub(crate) fn channels(self) -> usize {
+        match self {
+            GradientMode::Luma => 1,
+            GradientMode::MaxChannel | GradientMode::DiZenzo => 3,
+        }
+    }
+
+    /// Single gradient vector from the per-plane gradients `gx[c], gy[c]`. For `DiZenzo` the
+    /// sign of the vector is arbitrary, which NMS and sub-pixel fitting do not care about.
+    #[inline]
+    pub(crate) fn combine(self, gx: &[f32], gy: &[f32]) -> (f32, f32) {
+        match self {
+            GradientMode::Luma => (gx[0], gy[0]),
+            GradientMode::MaxChannel => {
+                let mut best = 0;
+                for c in 1..gx.len() {
+                    if gx[c] * gx[c] + gy[c] * gy[c] > gx[best] * gx[best] + gy[best] * gy[best] {
+                        best = c;
+                    }
+                }
+                (gx[best], gy[best])
+            }
+            GradientMode::DiZenzo => {
+                let (gxx, gyy, gxy) = structure_tensor(gx.iter().zip(gy).map(|(&x, &y)| (x as f64, y as f64)));
+                let lambda = largest_eigenvalue(gxx, gyy, gxy) / (gx.len() as f64);
+                let theta = 0.5 * (2.0 * gxy).atan2(gxx - gyy);
+                let m = lambda.sqrt();
+                ((m * theta.cos()) as f32, (m * theta.sin()) as f32)
+            }
+        }
+    }
+
+    /// Squared gradient magnitude from integer per-plane gradients, as used by Sobel.
+    #[inline]
+    pub(crate) fn combine_sq(self, gx: &[i32], gy: &[i32]) -> i32 {
+        let sq = |c: usize| gx[c].saturating_mul(gx[c]).saturating_add(gy[c].saturating_mul(gy[c]));
+        match self {
+            GradientMode::Luma => sq(0),
+            GradientMode::MaxChannel => (0..gx.len()).map(sq).max().unwrap_or(0),
+            GradientMode::DiZenzo => {
+                let (gxx, gyy, gxy) = structure_tensor(gx.iter().zip(gy).map(|(&x, &y)| (x as f64, y as f64)));
+                (largest_eigenvalue(gxx, gyy, gxy) / (gx.len() as f64)).round() as i32
+            }
+        }
+    }
+}
+
+/// Plane values of one RGBA pixel for `mode`: `[luma]` or `[r, g, b]`.
+#[inline]
+pub(crate) fn pixel_planes(mode: GradientMode, px: &[u8]) -> [i32; 3] {
+    let (r, g, b) = (px[0] as i32, px[1] as i32, px[2] as i32);
+    match mode {
+        // Integer grayscale: approx 0.299R + 0.587G + 0.114B
+        GradientMode::Luma => [(77 * r + 150 * g + 29 * b) >> 8, 0, 0],
+        GradientMode::MaxChannel | GradientMode::DiZenzo => [r, g, b],
+    }
+}
+
+/// Sums `gx^2`, `gy^2` and `gx * gy` over the planes.
+#[inline]
+fn structure_tensor(grads: impl Iterator<Item = (f64, f64)>) -> (f64, f64, f64) {
+    grads.fold((0.0, 0.0, 0.0), |(xx, yy, xy), (x, y)| (xx + x * x, yy + y * y, xy + x * y))
+}
+
+#[inline]
+fn largest_eigenvalue(gxx: f64, gyy: f64, gxy: f64) -> f64 {
+    0.5 * (gxx + gyy + ((gxx - gyy) * (gxx - gyy) + 4.0 * gxy * gxy).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::border::BorderMode;
+    use crate::filters::edge_sobel::{edge_detection_sobel_with, SobelMode};
+    use crate::filters::test_util::lcg;
+    use crate::image::RgbaImageMut;
+
+    const MODES: [GradientMode; 3] = [GradientMode::Luma, GradientMode::MaxChannel, GradientMode::DiZenzo];
+
+    #[test]
+    fn colour_modes_match_luma_on_gray_gradients() {
+        let mut seed = 19u64;
+        for _ in 0..500 {
+            let gx = (lcg(&mut seed) % 2041) as i32 - 1020;
+            let gy = (lcg(&mut seed) % 2041) as i32 - 1020;
+            let luma_sq = GradientMode::Luma.combine_sq(&[gx], &[gy]);
+            for mode in [GradientMode::MaxChannel, GradientMode::DiZenzo] {
+                assert_eq!(mode.combine_sq(&[gx; 3], &[gy; 3]), luma_sq, "{:?} ({}, {})", mode, gx, gy);
+
+                let (fx, fy) = (gx as f32, gy as f32);
+                let (cx, cy) = mode.combine(&[fx; 3], &[fy; 3]);
+                // Same magnitude; Di Zenzo's vector may point the other way
+                assert!((cx * cx + cy * cy - (fx * fx + fy * fy)).abs() <= 1e-3 * (fx * fx + fy * fy).max(1.0));
+                assert!((cx * fy - cy * fx).abs() <= 1e-3 * (fx * fx + fy * fy).max(1.0), "{:?} not parallel", mode);
+            }
+        }
+    }
+
+    #[test]
+    fn sobel_on_gray_image_is_the_same_in_every_mode() {
+        let mut seed = 7u64;
+        let gray: Vec<u8> = (0..12 * 9).map(|_| (lcg(&mut seed) % 256) as u8).collect();
+        for &v in &gray {
+            assert_eq!(pixel_planes(GradientMode::Luma, &[v, v, v, 255])[0], v as i32);
+        }
+        let outputs: Vec<Vec<u8>> = MODES
+            .iter()
+            .map(|&mode| {
+                let mut data: Vec<u8> = gray.iter().flat_map(|&v| [v, v, v, 255]).collect();
+                let mut img = RgbaImageMut::new(&mut data, 12, 9).unwrap();
+                edge_detection_sobel_with(&mut img, SobelMode::Magnitude, BorderMode::Reflect101, mode).unwrap();
+                data
+            })
+            .collect();
+        assert_eq!(outputs[0], outputs[1]);
+        assert_eq!(outputs[0], outputs[2]);
+    }
+}
@@ -1,17 +1,203 @@
-use wasm_bindgen::prelude::*;
-
-#[wasm_bindgen]
-pub fn grayscale(ptr: *mut u8, width: u32, height: u32) {
-    let len = (width * height * 4) as usize;
-    let data = unsafe { std::slice::from_raw_parts_mut(ptr, len) };
-    for i in (0..len).step_by(4) {
-        let r = data[i] as f64;
-        let g = data[i + 1] as f64;
-        let b = data[i + 2] as f64;
-        let y = (0.21 * r + 0.72 * g + 0.07 * b).round() as u8;
-        data[i] = y;
-        data[i + 1] = y;
-        data[i + 2] = y;
-        // Alpha (data[i + 3]) tetap
-    }
-}
\ No newline at end of file
+use crate::error::ImageError;
+use crate::image::{GrayImage, GrayImageMut, RgbaImage, RgbaImageMut};
+use crate::profile::Profiler;
+
+/// How RGB is reduced to a single gray value.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrayscaleMethod {
+    /// `0.21R + 0.72G + 0.07B`, the weights of the original `grayscale` and `grayscaleJS`.
+    Legacy = 0,
+    /// Rec. 601 luma `0.299R + 0.587G + 0.114B`. Sobel and Canny use its fixed-point form
+    /// `(77R + 150G + 29B) >> 8`, which can come out one level lower.
+    Bt601 = 1,
+    /// Rec. 709 / sRGB luma `0.2126R + 0.7152G + 0.0722B`.
+    Bt709 = 2,
+    /// Rec. 2100 (and 2020) luma `0.2627R + 0.6780G + 0.0593B`.
+    Bt2100 = 3,
+    /// `(R + G + B) / 3`.
+    Average = 4,
+    /// CIE L* of the linear-light luminance, scaled to 0..255. Perceptually uniform steps.
+    Lightness = 5,
+    /// HSL lightness `(max + min) / 2`, as in image editors' "desaturate".
+    Desaturate = 6,
+    Red = 7,
+    Green = 8,
+    Blue = 9,
+    /// Rec. 709 luminance computed in linear light: sRGB decode, weight, sRGB encode.
+    /// Keeps the perceived brightness of saturated colours better than gamma-space luma.
+    Linear = 10,
+}
+
+/// Original behaviour, see [`GrayscaleMethod::Legacy`].
+pub fn grayscale(img: &mut RgbaImageMut) {
+    grayscale_with(img, GrayscaleMethod::Legacy);
+}
+
+pub fn grayscale_with(img: &mut RgbaImageMut, method: GrayscaleMethod) {
+    let mut profiler = Profiler::start("grayscale");
+    let decode = srgb_decode_table();
+    for y in 0..img.height() as usize {
+        let data = img.row_mut(y);
+        for px in data.chunks_exact_mut(4) {
+            let y = gray_value(method, px[0], px[1], px[2], &decode);
+            px[0] = y;
+            px[1] = y;
+            px[2] = y;
+            // Alpha (px[3]) tetap
+        }
+    }
+    profiler.stage("pixels");
+}
+
+/// Like [`grayscale_with`], but writes one byte per pixel into `dst`, which must have the
+/// same size as `src`. `src` is left untouched and alpha is dropped.
+pub fn grayscale_into(
+    src: RgbaImage,
+    dst: &mut GrayImageMut,
+    method: GrayscaleMethod
+) -> Result<(), ImageError> {
+    dst.require_size(src.width(), src.height())?;
+    let mut profiler = Profiler::start("grayscale_into");
+    let decode = srgb_decode_table();
+    for y in 0..src.height() as usize {
+        for (out, px) in dst.row_mut(y).iter_mut().zip(src.row(y).chunks_exact(4)) {
+            *out = gray_value(method, px[0], px[1], px[2], &decode);
+        }
+    }
+    profiler.stage("pixels");
+    Ok(())
+}
+
+/// Spread a single-channel image over R, G and B of `dst` for display; alpha becomes 255.
+pub fn expand_gray_to_rgba(src: GrayImage, dst: &mut RgbaImageMut) -> Result<(), ImageError> {
+    if dst.width() != src.width() || dst.height() != src.height() {
+        return Err(ImageError::InvalidParameter { name: "dst" });
+    }
+    for y in 0..src.height() as usize {
+        for (px, &v) in dst.row_mut(y).chunks_exact_mut(4).zip(src.row(y)) {
+            px.copy_from_slice(&[v, v, v, 255]);
+        }
+    }
+    Ok(())
+}
+
+/// Gray value of one pixel. `decode` is [`srgb_decode_table`], read by the linear-light methods only.
+#[inline]
+pub(crate) fn gray_value(method: GrayscaleMethod, r: u8, g: u8, b: u8, decode: &[f32; 256]) -> u8 {
+    let weighted = |wr: f64, wg: f64, wb: f64| -> u8 {
+        (wr * (r as f64) + wg * (g as f64) + wb * (b as f64)).round() as u8
+    };
+    match method {
+        GrayscaleMethod::Legacy => weighted(0.21, 0.72, 0.07),
+        GrayscaleMethod::Bt601 => weighted(0.299, 0.587, 0.114),
+        GrayscaleMethod::Bt709 => weighted(0.2126, 0.7152, 0.0722),
+        GrayscaleMethod::Bt2100 => weighted(0.2627, 0.678, 0.0593),
+        GrayscaleMethod::Average => (((r as u32) + (g as u32) + (b as u32) + 1) / 3) as u8,
+        GrayscaleMethod::Lightness => {
+            let y = linear_luminance(r, g, b, decode);
+            // CIE 1976 L* (0..100) of relative luminance Y
+            let l = if y > 216.0 / 24389.0 { 116.0 * y.cbrt() - 16.0 } else { y * 24389.0 / 27.0 };
+            (l * 2.55).round().clamp(0.0, 255.0) as u8
+        }
+        GrayscaleMethod::Desaturate => {
+            let max = r.max(g).max(b) as u32;
+            let min = r.min(g).min(b) as u32;
+            (max + min).div_ceil(2) as u8
+        }
+        GrayscaleMethod::Red => r,
+        GrayscaleMethod::Green => g,
+        GrayscaleMethod::Blue => b,
+        GrayscaleMethod::Linear => srgb_encode(linear_luminance(r, g, b, decode)),
+    }
+}
+
+/// sRGB byte -> linear light 0..1.
+pub(crate) fn srgb_decode_table() -> [f32; 256] {
+    let mut table = [0.0f32; 256];
+    for (i, v) in table.iter_mut().enumerate() {
+        let c = (i as f32) / 255.0;
+        *v = if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+    }
+    table
+}
+
+/// Linear light 0..1 -> sRGB byte.
+pub(crate) fn srgb_encode(v: f32) -> u8 {
+    let c = if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+    (c * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Rec. 709 relative luminance Y (0..1) of an sRGB pixel.
+#[inline]
+fn linear_luminance(r: u8, g: u8, b: u8, decode: &[f32; 256]) -> f32 {
+    0.2126 * decode[r as usize] + 0.7152 * decode[g as usize] + 0.0722 * decode[b as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEIGHTED: [GrayscaleMethod; 6] = [
+        GrayscaleMethod::Legacy,
+        GrayscaleMethod::Bt601,
+        GrayscaleMethod::Bt709,
+        GrayscaleMethod::Bt2100,
+        GrayscaleMethod::Average,
+        GrayscaleMethod::Desaturate,
+    ];
+
+    #[test]
+    fn primaries_give_known_values() {
+        let decode = srgb_decode_table();
+        let cases = [
+            (GrayscaleMethod::Legacy, [54, 184, 18]),
+            (GrayscaleMethod::Bt601, [76, 150, 29]),
+            (GrayscaleMethod::Bt709, [54, 182, 18]),
+            (GrayscaleMethod::Bt2100, [67, 173, 15]),
+            (GrayscaleMethod::Average, [85, 85, 85]),
+            (GrayscaleMethod::Desaturate, [128, 128, 128]),
+            (GrayscaleMethod::Red, [255, 0, 0]),
+            (GrayscaleMethod::Green, [0, 255, 0]),
+            (GrayscaleMethod::Blue, [0, 0, 255]),
+            (GrayscaleMethod::Linear, [127, 220, 76]),
+            (GrayscaleMethod::Lightness, [136, 224, 82]),
+        ];
+        for (method, expected) in cases {
+            let got = [
+                gray_value(method, 255, 0, 0, &decode),
+                gray_value(method, 0, 255, 0, &decode),
+                gray_value(method, 0, 0, 255, &decode),
+            ];
+            assert_eq!(got, expected, "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn gray_pixels_keep_their_level() {
+        let decode = srgb_decode_table();
+        for v in 0..=255u8 {
+            for method in WEIGHTED.into_iter().chain([GrayscaleMethod::Linear]) {
+                assert_eq!(gray_value(method, v, v, v, &decode), v, "{:?} at {}", method, v);
+            }
+        }
+        assert_eq!(gray_value(GrayscaleMethod::Lightness, 0, 0, 0, &decode), 0);
+        assert_eq!(gray_value(GrayscaleMethod::Lightness, 255, 255, 255, &decode), 255);
+    }
+
+    #[test]
+    fn grayscale_into_matches_in_place() {
+        let src = [255, 0, 0, 10, 30, 200, 90, 255, 7, 7, 7, 0];
+        let mut in_place = src;
+        grayscale_with(&mut RgbaImageMut::new(&mut in_place, 3, 1).unwrap(), GrayscaleMethod::Bt709);
+        assert_eq!(in_place[3], 10);
+
+        let mut gray = [0u8; 3];
+        let rgba = RgbaImage::new(&src, 3, 1).unwrap();
+        grayscale_into(rgba, &mut GrayImageMut::new(&mut gray, 3, 1).unwrap(), GrayscaleMethod::Bt709).unwrap();
+        assert_eq!(gray, [in_place[0], in_place[4], in_place[8]]);
+
+        let rotated = &mut GrayImageMut::new(&mut gray, 1, 3).unwrap();
+        assert!(grayscale_into(rgba, rotated, GrayscaleMethod::Bt709).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::image::RgbaImageMut;
+use crate::profile::Profiler;
+
+/// One 256-entry lookup table per channel (R, G, B, A): channel value `v` becomes `table[v]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut {
+    tables: [[u8; 256]; 4],
+}
+
+impl Lut {
+    /// Leaves every channel unchanged.
+    pub fn identity() -> Lut {
+        let mut table = [0u8; 256];
+        for (i, v) in table.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+        Lut { tables: [table; 4] }
+    }
+
+    pub fn new(r: [u8; 256], g: [u8; 256], b: [u8; 256], a: [u8; 256]) -> Lut {
+        Lut { tables: [r, g, b, a] }
+    }
+
+    /// The same table for R, G and B; alpha is kept.
+    pub fn rgb(table: [u8; 256]) -> Lut {
+        let mut lut = Lut::identity();
+        lut.tables[..3].fill(table);
+        lut
+    }
+
+    /// RGB table from `f(v)` for every input level, rounded and clamped to 0..255.
+    pub(crate) fn rgb_from_fn(f: impl Fn(f32) -> f32) -> Lut {
+        let mut table = [0u8; 256];
+        for (i, v) in table.iter_mut().enumerate() {
+            *v = f(i as f32).round().clamp(0.0, 255.0) as u8;
+        }
+        Lut::rgb(table)
+    }
+
+    /// Table of channel `c` (0 = R .. 3 = A).
+    pub fn table(&self, c: usize) -> &[u8; 256] {
+        &self.tables[c]
+    }
+
+    /// `other` applied after `self`, as one table per channel.
+    pub fn then(&self, other: &Lut) -> Lut {
+        let mut tables = self.tables;
+        for (table, next) in tables.iter_mut().zip(&other.tables) {
+            for v in table.iter_mut() {
+                *v = next[*v as usize];
+            }
+        }
+        Lut { tables }
+    }
+}
+
+/// Look up every channel of every pixel in `lut`.
+pub fn apply_lut(img: &mut RgbaImageMut, lut: &Lut) {
+    let mut profiler = Profiler::start("apply_lut");
+    lut_pixels(img, lut);
+    profiler.stage("pixels");
+}
+
+/// [`apply_lut`] without a profile of its own, for filters that build a table and time it.
+pub(crate) fn lut_pixels(img: &mut RgbaImageMut, lut: &Lut) {
+    let [r, g, b, a] = &lut.tables;
+    for y in 0..img.height() as usize {
+        for px in img.row_mut(y).chunks_exact_mut(4) {
+            px[0] = r[px[0] as usize];
+            px[1] = g[px[1] as usize];
+            px[2] = b[px[2] as usize];
+            px[3] = a[px[3] as usize];
+        }
+    }
+}
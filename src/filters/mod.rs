@@ -1,5 +1,18 @@
 pub mod blur;
+pub mod border;
+pub mod color_matrix;
+pub mod contour;
+pub mod curves;
 pub mod edge_sobel;
+pub mod gradient;
 pub mod grayscale;
+pub mod lut;
 pub mod sepia;
-pub mod edge_canny;
\ No newline at end of file
+pub mod svg;
+pub mod tone;
+pub mod edge_canny;
+pub mod percentile;
+pub mod threshold;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,126 @@
+//! Percentile thresholds shared by the Sobel and Canny detectors.
+//!
+//! Both return exactly what the JavaScript implementation computes:
+//! 1. Filter out zero values (background/flat regions)
+//! 2. Sort in ascending order
+//! 3. Return value at floor(length * percentile), clamped to the last element
+//!
+//! but without the full sort: only the element at that rank is located, in O(n).
+
+/// Coarse buckets for the integer histogram; the bucket holding the rank is then searched exactly.
+const BUCKETS_LOG2: u32 = 12;
+
+/// Rank of the percentile among `len` sorted values (floor in f32 to match JavaScript).
+#[inline]
+fn percentile_index(len: usize, percentile: f32) -> usize {
+    let index = ((len as f32) * percentile).floor() as usize;
+    index.min(len - 1)
+}
+
+/// Percentile of the non-zero values of a non-negative integer signal such as squared
+/// Sobel magnitudes. A histogram over the top bits of each value finds the bucket that
+/// holds the requested rank; only that bucket is then selected exactly.
+pub fn percentile_threshold_i32(values: &[i32], percentile: f32) -> i32 {
+    let mut count = 0usize;
+    let mut max = 0i32;
+    for &v in values {
+        if v > 0 {
+            count += 1;
+            max = max.max(v);
+        }
+    }
+    if count == 0 {
+        return 0; // All zeros, no edges
+    }
+    let rank = percentile_index(count, percentile);
+
+    let bits = 32 - (max as u32).leading_zeros();
+    let shift = bits.saturating_sub(BUCKETS_LOG2);
+    let mut hist = vec![0usize; ((max >> shift) as usize) + 1];
+    for &v in values {
+        if v > 0 {
+            hist[(v >> shift) as usize] += 1;
+        }
+    }
+
+    let mut below = 0usize;
+    let mut bucket = 0usize;
+    for (b, &c) in hist.iter().enumerate() {
+        if below + c > rank {
+            bucket = b;
+            break;
+        }
+        below += c;
+    }
+
+    if shift == 0 {
+        // One value per bucket: the bucket index is the value
+        return bucket as i32;
+    }
+    let mut in_bucket: Vec<i32> = values
+        .iter()
+        .copied()
+        .filter(|&v| v > 0 && ((v >> shift) as usize) == bucket)
+        .collect();
+    *in_bucket.select_nth_unstable(rank - below).1
+}
+
+/// Percentile of the positive values of an f32 signal such as NMS magnitudes, via quickselect.
+pub fn percentile_threshold_f32(values: &[f32], percentile: f32) -> f32 {
+    let mut non_zero: Vec<f32> = values
+        .iter()
+        .copied()
+        .filter(|&v| v > 0.0)
+        .collect();
+
+    if non_zero.is_empty() {
+        return 0.0; // All zeros, no edges
+    }
+
+    let rank = percentile_index(non_zero.len(), percentile);
+    *non_zero.select_nth_unstable_by(rank, f32::total_cmp).1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::test_util::lcg;
+
+    /// The previous sort-based implementations, kept as the reference.
+    fn sorted_i32(values: &[i32], percentile: f32) -> i32 {
+        let mut non_zero: Vec<i32> = values.iter().copied().filter(|&v| v > 0).collect();
+        if non_zero.is_empty() {
+            return 0;
+        }
+        non_zero.sort_unstable();
+        let index = ((non_zero.len() as f32) * percentile).floor() as usize;
+        non_zero[index.min(non_zero.len() - 1)]
+    }
+
+    fn sorted_f32(values: &[f32], percentile: f32) -> f32 {
+        let mut non_zero: Vec<f32> = values.iter().copied().filter(|&v| v > 0.0).collect();
+        if non_zero.is_empty() {
+            return 0.0;
+        }
+        non_zero.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let index = ((non_zero.len() as f32) * percentile).floor() as usize;
+        non_zero[index.min(non_zero.len() - 1)]
+    }
+
+    #[test]
+    fn matches_sort_based_percentile() {
+        const PERCENTILES: [f32; 9] = [0.0, 0.1, 0.5, 0.65, 0.7, 0.75, 0.85, 0.9, 1.0];
+        let mut seed = 42u64;
+        for &(len, range) in &[(0usize, 1u32), (1, 5), (17, 3), (1000, 50), (5000, 2_080_800), (4096, 1 << 30)] {
+            // Roughly a third zeros, like flat image regions
+            let ints: Vec<i32> = (0..len)
+                .map(|_| if lcg(&mut seed).is_multiple_of(3) { 0 } else { (lcg(&mut seed) % range) as i32 })
+                .collect();
+            let floats: Vec<f32> = ints.iter().map(|&v| (v as f32).sqrt() * 1.37).collect();
+            for &p in &PERCENTILES {
+                assert_eq!(percentile_threshold_i32(&ints, p), sorted_i32(&ints, p), "i32 len={} p={}", len, p);
+                assert_eq!(percentile_threshold_f32(&floats, p), sorted_f32(&floats, p), "f32 len={} p={}", len, p);
+            }
+        }
+    }
+}
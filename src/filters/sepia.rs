@@ -1,22 +1,33 @@
-use wasm_bindgen::prelude::*;
+use crate::error::ImageError;
+use crate::filters::color_matrix::{color_matrix, ColorMatrix};
+use crate::image::RgbaImageMut;
+use crate::profile::Profiler;
 
-#[wasm_bindgen]
-pub fn sepia(ptr: *mut u8, width: u32, height: u32) {
-    let len = (width * height * 4) as usize;
-    let data = unsafe { std::slice::from_raw_parts_mut(ptr, len) };
+pub fn sepia(img: &mut RgbaImageMut) {
+    let mut profiler = Profiler::start("sepia");
+    for y in 0..img.height() as usize {
+        let data = img.row_mut(y);
+        for px in data.chunks_exact_mut(4) {
+            let r = px[0] as f64;
+            let g = px[1] as f64;
+            let b = px[2] as f64;
 
-    for i in (0..len).step_by(4) {
-        let r = data[i] as f64;
-        let g = data[i + 1] as f64;
-        let b = data[i + 2] as f64;
+            let tr = (0.393 * r + 0.769 * g + 0.189 * b).min(255.0);
+            let tg = (0.349 * r + 0.686 * g + 0.168 * b).min(255.0);
+            let tb = (0.272 * r + 0.534 * g + 0.131 * b).min(255.0);
 
-        let tr = (0.393 * r + 0.769 * g + 0.189 * b).min(255.0);
-        let tg = (0.349 * r + 0.686 * g + 0.168 * b).min(255.0);
-        let tb = (0.272 * r + 0.534 * g + 0.131 * b).min(255.0);
-
-        data[i] = tr as u8;
-        data[i + 1] = tg as u8;
-        data[i + 2] = tb as u8;
-        // Alpha tetap
+            px[0] = tr as u8;
+            px[1] = tg as u8;
+            px[2] = tb as u8;
+            // Alpha tetap
+        }
     }
-}
\ No newline at end of file
+    profiler.stage("pixels");
+}
+
+/// Sepia blended with the original: 0 leaves the image unchanged, 1 is full sepia. Goes through
+/// [`color_matrix`], which rounds where [`sepia`] truncates, so channels can come out one level higher.
+pub fn sepia_with(img: &mut RgbaImageMut, intensity: f32) -> Result<(), ImageError> {
+    color_matrix(img, &ColorMatrix::sepia().with_intensity(intensity)?);
+    Ok(())
+}
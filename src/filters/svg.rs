@@ -0,0 +1,124 @@
+use std::fmt::Write;
+
+use crate::error::ImageError;
+use crate::filters::contour::{thin_mask, trace_contours, Contours};
+use crate::image::RgbaImage;
+
+/// Look of the exported outlines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgStyle {
+    /// Any CSS colour, e.g. `"#ff0000"` or `"black"`.
+    pub stroke: String,
+    /// Line width in pixels of the source image.
+    pub stroke_width: f32,
+}
+
+impl Default for SvgStyle {
+    fn default() -> SvgStyle {
+        SvgStyle { stroke: "#000000".to_string(), stroke_width: 1.0 }
+    }
+}
+
+impl SvgStyle {
+    fn validate(&self) -> Result<(), ImageError> {
+        // Rejecting markup characters keeps the attribute well-formed
+        if self.stroke.is_empty() || self.stroke.contains(['"', '\'', '<', '>', '&']) {
+            return Err(ImageError::InvalidParameter { name: "stroke" });
+        }
+        if !self.stroke_width.is_finite() || self.stroke_width <= 0.0 {
+            return Err(ImageError::InvalidParameter { name: "stroke_width" });
+        }
+        Ok(())
+    }
+}
+
+/// SVG document with one `<path>` per chain, sized like the traced image so the outlines
+/// overlay it exactly. Closed chains end in `Z`.
+pub fn contours_to_svg(contours: &Contours, style: &SvgStyle) -> Result<String, ImageError> {
+    style.validate()?;
+    let (w, h) = (contours.width(), contours.height());
+
+    let mut svg = String::new();
+    // Writing into a String cannot fail
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">"
+    );
+    let _ = writeln!(
+        svg,
+        "<g fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\">",
+        style.stroke,
+        style.stroke_width
+    );
+    for chain in contours.iter() {
+        let n = chain.len() / 2;
+        let closed = n > 2 && chain[0] == chain[2 * n - 2] && chain[1] == chain[2 * n - 1];
+        let end = if closed { n - 1 } else { n };
+
+        svg.push_str("<path d=\"");
+        for i in 0..end {
+            let _ = write!(svg, "{}{} {}", if i == 0 { "M" } else { " L" }, chain[2 * i], chain[2 * i + 1]);
+        }
+        if closed {
+            svg.push_str(" Z");
+        }
+        svg.push_str("\"/>\n");
+    }
+    svg.push_str("</g>\n</svg>\n");
+    Ok(svg)
+}
+
+/// Vectorize an edge map such as the output of Sobel or Canny: pixels whose red channel is at
+/// least 128 are edges. Thick edges are thinned to their centre line before tracing, and the
+/// chains are simplified with Douglas-Peucker when `epsilon` is given.
+pub fn edge_mask_to_svg(img: RgbaImage, epsilon: Option<f32>, style: &SvgStyle) -> Result<String, ImageError> {
+    style.validate()?;
+    let w = img.width() as usize;
+    let h = img.height() as usize;
+
+    let mut mask = Vec::with_capacity(w * h);
+    for y in 0..h {
+        mask.extend(img.row(y).chunks_exact(4).map(|px| px[0] >= 128));
+    }
+    let mut contours = trace_contours(&thin_mask(&mask, w, h), w, h);
+    if let Some(epsilon) = epsilon {
+        contours = contours.simplify(epsilon)?;
+    }
+    contours_to_svg(&contours, style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Opaque `w x h` edge map with white pixels where `rows` has `#`.
+    fn edge_map(rows: &[&str]) -> Vec<u8> {
+        rows.iter()
+            .flat_map(|r| r.chars())
+            .flat_map(|c| if c == '#' { [255, 255, 255, 255] } else { [0, 0, 0, 255] })
+            .collect()
+    }
+
+    #[test]
+    fn ring_becomes_one_closed_path() {
+        let data = edge_map(&["......", ".####.", ".#..#.", ".#..#.", ".####.", "......"]);
+        let svg = edge_mask_to_svg(RgbaImage::new(&data, 6, 6).unwrap(), Some(0.1), &SvgStyle::default()).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"6\" height=\"6\""));
+        assert_eq!(svg.matches("<path ").count(), 1);
+        assert!(svg.contains(" Z\"/>"));
+    }
+
+    #[test]
+    fn markup_in_stroke_is_rejected() {
+        let data = edge_map(&["...", ".#.", "..."]);
+        let img = RgbaImage::new(&data, 3, 3).unwrap();
+        for stroke in ["red\" onload=\"x", "it's", "<b>", "a>b", "a&b", ""] {
+            let style = SvgStyle { stroke: stroke.to_string(), stroke_width: 1.0 };
+            assert_eq!(edge_mask_to_svg(img, None, &style).err(), Some(ImageError::InvalidParameter { name: "stroke" }));
+        }
+        let style = SvgStyle { stroke_width: 0.0, ..SvgStyle::default() };
+        assert_eq!(edge_mask_to_svg(img, None, &style).err(), Some(ImageError::InvalidParameter { name: "stroke_width" }));
+        let style = SvgStyle { stroke: "rgb(255, 0, 0)".to_string(), stroke_width: 2.5 };
+        assert!(edge_mask_to_svg(img, None, &style).unwrap().contains("stroke=\"rgb(255, 0, 0)\" stroke-width=\"2.5\""));
+    }
+}
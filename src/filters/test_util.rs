@@ -0,0 +1,7 @@
+//! Helpers shared by the filter unit tests.
+
+/// Deterministic LCG so the tests need no extra dependencies.
+pub(crate) fn lcg(seed: &mut u64) -> u32 {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (*seed >> 33) as u32
+}
@@ -0,0 +1,244 @@
+use crate::error::ImageError;
+use crate::filters::gradient::{pixel_planes, GradientMode};
+use crate::image::RgbaImageMut;
+use crate::profile::Profiler;
+
+/// Upper bound on the classes of [`threshold_otsu_multi`].
+pub const MAX_OTSU_CLASSES: usize = 16;
+
+/// Binarize on luma: white where the luma is above `level`, black elsewhere. Alpha is kept.
+pub fn threshold_fixed(img: &mut RgbaImageMut, level: u8) {
+    let mut profiler = Profiler::start("threshold_fixed");
+    apply_levels(img, &[level]);
+    profiler.stage("pixels");
+}
+
+/// Binarize at the Otsu level of the luma histogram; returns the level used.
+pub fn threshold_otsu(img: &mut RgbaImageMut) -> u8 {
+    let mut profiler = Profiler::start("threshold_otsu");
+    let level = otsu_level(&luma_histogram(img));
+    profiler.stage("histogram");
+    apply_levels(img, &[level]);
+    profiler.stage("pixels");
+    level
+}
+
+/// Posterize into `classes` evenly spaced grays (2 = black and white) at the levels that
+/// maximize the between-class variance of the luma histogram. Returns the `classes - 1`
+/// ascending levels; luma above `levels[i]` belongs to class `i + 1` or higher.
+pub fn threshold_otsu_multi(img: &mut RgbaImageMut, classes: usize) -> Result<Vec<u8>, ImageError> {
+    if !(2..=MAX_OTSU_CLASSES).contains(&classes) {
+        return Err(ImageError::InvalidParameter { name: "classes" });
+    }
+    let mut profiler = Profiler::start("threshold_otsu_multi");
+    let levels = otsu_levels(&luma_histogram(img), classes);
+    profiler.stage("histogram");
+    apply_levels(img, &levels);
+    profiler.stage("pixels");
+    Ok(levels)
+}
+
+/// Otsu's method: the level `t` that maximizes the between-class variance when the
+/// histogram is split into `0..=t` (background) and `t+1..` (foreground).
+/// Returns 0 for an empty or single-valued histogram.
+pub fn otsu_level(hist: &[u32; 256]) -> u8 {
+    let total: u64 = hist.iter().map(|&c| c as u64).sum();
+    if total == 0 {
+        return 0;
+    }
+    let sum_all: f64 = hist
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| (i as f64) * (c as f64))
+        .sum();
+
+    let mut weight_bg = 0u64;
+    let mut sum_bg = 0.0f64;
+    let mut best_level = 0u8;
+    let mut best_var = -1.0f64;
+
+    for (t, &count) in hist.iter().enumerate() {
+        weight_bg += count as u64;
+        if weight_bg == 0 {
+            continue;
+        }
+        let weight_fg = total - weight_bg;
+        if weight_fg == 0 {
+            break;
+        }
+        sum_bg += (t as f64) * (count as f64);
+
+        let mean_bg = sum_bg / (weight_bg as f64);
+        let mean_fg = (sum_all - sum_bg) / (weight_fg as f64);
+        let diff = mean_bg - mean_fg;
+        let var = (weight_bg as f64) * (weight_fg as f64) * diff * diff;
+        if var > best_var {
+            best_var = var;
+            best_level = t as u8;
+        }
+    }
+    best_level
+}
+
+/// Multi-level Otsu by dynamic programming over the histogram, O(classes * 256^2).
+/// With two classes this is the same level as [`otsu_level`].
+pub fn otsu_levels(hist: &[u32; 256], classes: usize) -> Vec<u8> {
+    // Prefix sums of counts and of level * count; class [a, b) has score sum^2 / count
+    let mut count = [0.0f64; 257];
+    let mut sum = [0.0f64; 257];
+    for (i, &c) in hist.iter().enumerate() {
+        count[i + 1] = count[i] + (c as f64);
+        sum[i + 1] = sum[i] + (i as f64) * (c as f64);
+    }
+    let score = |a: usize, b: usize| -> f64 {
+        let n = count[b] - count[a];
+        if n == 0.0 { 0.0 } else { (sum[b] - sum[a]) * (sum[b] - sum[a]) / n }
+    };
+
+    // best[k][j]: best score of splitting levels [0, j) into k + 1 classes; from[k][j]: start of the last class
+    let mut best = vec![[f64::NEG_INFINITY; 257]; classes];
+    let mut from = vec![[0usize; 257]; classes];
+    for (j, b) in best[0].iter_mut().enumerate() {
+        *b = score(0, j);
+    }
+    for k in 1..classes {
+        for j in (k + 1)..=256 {
+            for i in k..j {
+                let v = best[k - 1][i] + score(i, j);
+                if v > best[k][j] {
+                    best[k][j] = v;
+                    from[k][j] = i;
+                }
+            }
+        }
+    }
+
+    let mut levels = vec![0u8; classes - 1];
+    let mut end = 256;
+    for k in (1..classes).rev() {
+        end = from[k][end];
+        levels[k - 1] = (end - 1) as u8;
+    }
+    levels
+}
+
+/// Histogram of the integer luma used by the edge detectors.
+fn luma_histogram(img: &RgbaImageMut) -> [u32; 256] {
+    let mut hist = [0u32; 256];
+    for y in 0..img.height() as usize {
+        for px in img.row(y).chunks_exact(4) {
+            hist[pixel_planes(GradientMode::Luma, px)[0] as usize] += 1;
+        }
+    }
+    hist
+}
+
+/// Replace every pixel by the gray of its class, classes split at the ascending `levels`.
+fn apply_levels(img: &mut RgbaImageMut, levels: &[u8]) {
+    let top = levels.len() as u32;
+    let mut lut = [0u8; 256];
+    for (luma, out) in lut.iter_mut().enumerate() {
+        let class = levels.iter().filter(|&&l| luma > l as usize).count() as u32;
+        *out = ((class * 255 + top / 2) / top) as u8;
+    }
+    for y in 0..img.height() as usize {
+        for px in img.row_mut(y).chunks_exact_mut(4) {
+            let v = lut[pixel_planes(GradientMode::Luma, px)[0] as usize];
+            px[0] = v;
+            px[1] = v;
+            px[2] = v;
+            // Alpha tetap
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::test_util::lcg;
+
+    #[test]
+    fn two_class_levels_match_otsu_level() {
+        let mut seed = 7u64;
+        for round in 0..200 {
+            let mut hist = [0u32; 256];
+            // Two or three noisy peaks, sometimes with empty ranges
+            for _ in 0..(2 + round % 2) {
+                let centre = (lcg(&mut seed) % 256) as i32;
+                let spread = 1 + (lcg(&mut seed) % 40) as i32;
+                for _ in 0..500 {
+                    let v = centre + ((lcg(&mut seed) % (2 * spread as u32 + 1)) as i32) - spread;
+                    hist[v.clamp(0, 255) as usize] += 1;
+                }
+            }
+            assert_eq!(otsu_levels(&hist, 2), vec![otsu_level(&hist)], "round {}", round);
+        }
+    }
+
+    #[test]
+    fn multi_level_separates_peaks() {
+        let mut hist = [0u32; 256];
+        for (centre, n) in [(30usize, 400u32), (120, 300), (220, 500)] {
+            hist[centre - 5..=centre + 5].fill(n);
+        }
+        let levels = otsu_levels(&hist, 3);
+        assert!((35..115).contains(&levels[0]) && (125..215).contains(&levels[1]), "{:?}", levels);
+    }
+
+    /// One pixel per `(gray, alpha)`.
+    fn pixels(values: &[(u8, u8)]) -> Vec<u8> {
+        values.iter().flat_map(|&(v, a)| [v, v, v, a]).collect()
+    }
+
+    fn image(data: &mut [u8]) -> RgbaImageMut<'_> {
+        let w = (data.len() / 4) as u32;
+        RgbaImageMut::new(data, w, 1).unwrap()
+    }
+
+    #[test]
+    fn fixed_threshold_binarizes_luma_and_keeps_alpha() {
+        // Pure red has luma 76
+        let mut data = [pixels(&[(100, 255), (101, 128), (0, 0)]), vec![255, 0, 0, 7]].concat();
+        threshold_fixed(&mut image(&mut data), 100);
+        assert_eq!(data, [pixels(&[(0, 255), (255, 128), (0, 0)]), vec![0, 0, 0, 7]].concat());
+
+        let mut data = [255, 0, 0, 7];
+        threshold_fixed(&mut image(&mut data), 75);
+        assert_eq!(data, [255, 255, 255, 7]);
+    }
+
+    #[test]
+    fn otsu_threshold_splits_two_clusters() {
+        let mut data = pixels(&[(40, 10), (42, 20), (200, 30), (198, 40), (41, 50)]);
+        let level = threshold_otsu(&mut image(&mut data));
+        assert!((42..198).contains(&level), "{}", level);
+        assert_eq!(data, pixels(&[(0, 10), (0, 20), (255, 30), (255, 40), (0, 50)]));
+    }
+
+    #[test]
+    fn multi_level_posterizes_into_evenly_spaced_grays() {
+        let mut data = pixels(&[(10, 255), (128, 9), (250, 255), (12, 255)]);
+        let levels = threshold_otsu_multi(&mut image(&mut data), 3).unwrap();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(data, pixels(&[(0, 255), (128, 9), (255, 255), (0, 255)]));
+
+        let mut data = pixels(&[(200, 255), (5, 255), (70, 255), (140, 255)]);
+        threshold_otsu_multi(&mut image(&mut data), 4).unwrap();
+        assert_eq!(data, pixels(&[(255, 255), (0, 255), (85, 255), (170, 255)]));
+    }
+
+    #[test]
+    fn class_count_is_bounded() {
+        let original = pixels(&[(10, 255), (200, 255)]);
+        for classes in [0, 1, MAX_OTSU_CLASSES + 1] {
+            let mut data = original.clone();
+            assert_eq!(
+                threshold_otsu_multi(&mut image(&mut data), classes),
+                Err(ImageError::InvalidParameter { name: "classes" })
+            );
+            assert_eq!(data, original);
+        }
+        let levels = threshold_otsu_multi(&mut image(&mut original.clone()), MAX_OTSU_CLASSES).unwrap();
+        assert_eq!(levels.len(), MAX_OTSU_CLASSES - 1);
+    }
+}
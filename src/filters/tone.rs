@@ -0,0 +1,149 @@
+use crate::error::ImageError;
+use crate::filters::grayscale::{srgb_decode_table, srgb_encode};
+use crate::filters::lut::{lut_pixels, Lut};
+use crate::image::RgbaImageMut;
+use crate::profile::Profiler;
+
+/// Shift R, G and B by `amount` of the full range: -1 is black, 0 unchanged, 1 white.
+pub fn brightness_lut(amount: f32) -> Result<Lut, ImageError> {
+    if !(-1.0..=1.0).contains(&amount) {
+        return Err(ImageError::InvalidParameter { name: "amount" });
+    }
+    Ok(Lut::rgb_from_fn(|v| v + amount * 255.0))
+}
+
+/// Scale R, G and B around mid-gray as CSS `contrast()`: 0 is flat gray, 1 unchanged.
+pub fn contrast_lut(factor: f32) -> Result<Lut, ImageError> {
+    if !factor.is_finite() || factor < 0.0 {
+        return Err(ImageError::InvalidParameter { name: "factor" });
+    }
+    Ok(Lut::rgb_from_fn(|v| (v - 127.5) * factor + 127.5))
+}
+
+/// `255 (v / 255)^(1 / gamma)`: above 1 brightens the midtones, below 1 darkens them.
+pub fn gamma_lut(gamma: f32) -> Result<Lut, ImageError> {
+    if !gamma.is_finite() || gamma <= 0.0 {
+        return Err(ImageError::InvalidParameter { name: "gamma" });
+    }
+    Ok(Lut::rgb_from_fn(|v| 255.0 * (v / 255.0).powf(1.0 / gamma)))
+}
+
+/// Multiply the linear light by `2^stops`, like a camera exposure change.
+pub fn exposure_lut(stops: f32) -> Result<Lut, ImageError> {
+    if !stops.is_finite() {
+        return Err(ImageError::InvalidParameter { name: "stops" });
+    }
+    let decode = srgb_decode_table();
+    let gain = stops.exp2();
+    let mut table = [0u8; 256];
+    for (v, &linear) in table.iter_mut().zip(&decode) {
+        *v = srgb_encode(linear * gain);
+    }
+    Ok(Lut::rgb(table))
+}
+
+/// Input levels: `black` maps to 0, `white` to 255, and `midtone` is the gamma applied in
+/// between (1 is linear, above 1 brightens).
+pub fn levels_lut(black: u8, white: u8, midtone: f32) -> Result<Lut, ImageError> {
+    if white <= black {
+        return Err(ImageError::InvalidParameter { name: "white" });
+    }
+    if !midtone.is_finite() || midtone <= 0.0 {
+        return Err(ImageError::InvalidParameter { name: "midtone" });
+    }
+    let (black, range) = (black as f32, (white - black) as f32);
+    Ok(Lut::rgb_from_fn(|v| 255.0 * ((v - black) / range).clamp(0.0, 1.0).powf(1.0 / midtone)))
+}
+
+pub fn brightness(img: &mut RgbaImageMut, amount: f32) -> Result<(), ImageError> {
+    apply_tone(img, "brightness", || brightness_lut(amount))
+}
+
+pub fn contrast(img: &mut RgbaImageMut, factor: f32) -> Result<(), ImageError> {
+    apply_tone(img, "contrast", || contrast_lut(factor))
+}
+
+pub fn gamma(img: &mut RgbaImageMut, gamma: f32) -> Result<(), ImageError> {
+    apply_tone(img, "gamma", || gamma_lut(gamma))
+}
+
+pub fn exposure(img: &mut RgbaImageMut, stops: f32) -> Result<(), ImageError> {
+    apply_tone(img, "exposure", || exposure_lut(stops))
+}
+
+pub fn levels(img: &mut RgbaImageMut, black: u8, white: u8, midtone: f32) -> Result<(), ImageError> {
+    apply_tone(img, "levels", || levels_lut(black, white, midtone))
+}
+
+/// Build the table and apply it, profiled as `filter` with "build_lut" and "pixels" stages.
+fn apply_tone(
+    img: &mut RgbaImageMut,
+    filter: &'static str,
+    build: impl FnOnce() -> Result<Lut, ImageError>
+) -> Result<(), ImageError> {
+    let mut profiler = Profiler::start(filter);
+    let lut = build()?;
+    profiler.stage("build_lut");
+    lut_pixels(img, &lut);
+    profiler.stage("pixels");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neutral_parameters_give_identity() {
+        let identity = Lut::identity();
+        assert_eq!(brightness_lut(0.0).unwrap(), identity);
+        assert_eq!(contrast_lut(1.0).unwrap(), identity);
+        assert_eq!(gamma_lut(1.0).unwrap(), identity);
+        assert_eq!(exposure_lut(0.0).unwrap(), identity);
+        assert_eq!(levels_lut(0, 255, 1.0).unwrap(), identity);
+    }
+
+    #[test]
+    fn levels_clamp_outside_black_and_white() {
+        let lut = levels_lut(50, 200, 1.0).unwrap();
+        let table = lut.table(0);
+        assert!(table[..=50].iter().all(|&v| v == 0));
+        assert!(table[200..].iter().all(|&v| v == 255));
+        assert_eq!(table[125], 128);
+        assert!(table.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(lut.table(3), Lut::identity().table(3));
+    }
+
+    #[test]
+    fn out_of_range_parameters_are_rejected() {
+        let invalid = |name| Err(ImageError::InvalidParameter { name });
+        assert_eq!(brightness_lut(1.5), invalid("amount"));
+        assert_eq!(brightness_lut(f32::NAN), invalid("amount"));
+        assert_eq!(contrast_lut(-0.5), invalid("factor"));
+        assert_eq!(contrast_lut(f32::INFINITY), invalid("factor"));
+        assert_eq!(gamma_lut(0.0), invalid("gamma"));
+        assert_eq!(exposure_lut(f32::INFINITY), invalid("stops"));
+        assert_eq!(levels_lut(100, 100, 1.0), invalid("white"));
+        assert_eq!(levels_lut(0, 255, 0.0), invalid("midtone"));
+
+        let mut data = [10u8, 20, 30, 40];
+        let mut img = RgbaImageMut::new(&mut data, 1, 1).unwrap();
+        assert_eq!(brightness(&mut img, 2.0), Err(ImageError::InvalidParameter { name: "amount" }));
+        assert_eq!(data, [10, 20, 30, 40]);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn tone_filters_profile_building_the_table() {
+        let mut data = [10u8, 20, 30, 40];
+        levels(&mut RgbaImageMut::new(&mut data, 1, 1).unwrap(), 5, 250, 1.2).unwrap();
+        let profile = crate::profile::last_profile().unwrap();
+        assert_eq!(profile.filter, "levels");
+        let stages: Vec<_> = profile.stages.iter().map(|s| s.name).collect();
+        assert_eq!(stages, ["build_lut", "pixels"]);
+
+        // A rejected call keeps the last good profile
+        assert!(gamma(&mut RgbaImageMut::new(&mut data, 1, 1).unwrap(), -1.0).is_err());
+        assert_eq!(crate::profile::last_profile(), Some(profile));
+    }
+}
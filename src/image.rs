@@ -0,0 +1,320 @@
+use crate::error::ImageError;
+
+/// Minimum byte length a buffer needs for `height` rows of `stride` bytes,
+/// the last of which only has to hold `width` pixels of `bpp` bytes.
+fn required_len(width: u32, height: u32, stride: usize, bpp: usize) -> Result<usize, ImageError> {
+    let row = (width as usize).checked_mul(bpp).ok_or(ImageError::Overflow)?;
+    if stride < row {
+        return Err(ImageError::InvalidStride { stride, min: row });
+    }
+    if height == 0 {
+        return Ok(0);
+    }
+    stride
+        .checked_mul((height - 1) as usize)
+        .and_then(|n| n.checked_add(row))
+        .ok_or(ImageError::Overflow)
+}
+
+fn check_len(len: usize, width: u32, height: u32, stride: usize, bpp: usize) -> Result<(), ImageError> {
+    let needed = required_len(width, height, stride, bpp)?;
+    if len < needed {
+        return Err(ImageError::BufferTooSmall { needed, available: len });
+    }
+    Ok(())
+}
+
+/// Borrowed read-only RGBA8 image. Rows are `stride` bytes apart; each row starts
+/// with `width * 4` bytes of pixel data, anything after that is padding and never read.
+#[derive(Clone, Copy)]
+pub struct RgbaImage<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl<'a> RgbaImage<'a> {
+    /// Tightly packed image (`stride == width * 4`).
+    pub fn new(data: &'a [u8], width: u32, height: u32) -> Result<Self, ImageError> {
+        let stride = (width as usize).checked_mul(4).ok_or(ImageError::Overflow)?;
+        RgbaImage::with_stride(data, width, height, stride)
+    }
+
+    pub fn with_stride(
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        stride: usize
+    ) -> Result<Self, ImageError> {
+        check_len(data.len(), width, height, stride, 4)?;
+        Ok(RgbaImage { data, width, height, stride })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Fails with `InvalidDimensions` unless both sides are at least `min` pixels.
+    pub fn require_min_size(&self, min: u32) -> Result<(), ImageError> {
+        if self.width < min || self.height < min {
+            return Err(ImageError::InvalidDimensions { width: self.width, height: self.height, min });
+        }
+        Ok(())
+    }
+
+    /// Pixel bytes of row `y`, without padding.
+    #[inline]
+    pub fn row(&self, y: usize) -> &'a [u8] {
+        let start = y * self.stride;
+        &self.data[start..start + (self.width as usize) * 4]
+    }
+
+    /// Byte offset of pixel `(x, y)` in the underlying slice.
+    #[inline]
+    pub fn offset(&self, x: usize, y: usize) -> usize {
+        y * self.stride + x * 4
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Copy the pixels into a tightly packed `width * height * 4` vector.
+    pub fn to_packed(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity((self.width as usize) * (self.height as usize) * 4);
+        for y in 0..self.height as usize {
+            out.extend_from_slice(self.row(y));
+        }
+        out
+    }
+}
+
+/// Borrowed mutable RGBA8 image; the in-place counterpart of [`RgbaImage`].
+pub struct RgbaImageMut<'a> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl<'a> RgbaImageMut<'a> {
+    /// Tightly packed image (`stride == width * 4`).
+    pub fn new(data: &'a mut [u8], width: u32, height: u32) -> Result<Self, ImageError> {
+        let stride = (width as usize).checked_mul(4).ok_or(ImageError::Overflow)?;
+        RgbaImageMut::with_stride(data, width, height, stride)
+    }
+
+    pub fn with_stride(
+        data: &'a mut [u8],
+        width: u32,
+        height: u32,
+        stride: usize
+    ) -> Result<Self, ImageError> {
+        check_len(data.len(), width, height, stride, 4)?;
+        Ok(RgbaImageMut { data, width, height, stride })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn require_min_size(&self, min: u32) -> Result<(), ImageError> {
+        self.as_image().require_min_size(min)
+    }
+
+    #[inline]
+    pub fn row(&self, y: usize) -> &[u8] {
+        let start = y * self.stride;
+        &self.data[start..start + (self.width as usize) * 4]
+    }
+
+    #[inline]
+    pub fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        let start = y * self.stride;
+        let end = start + (self.width as usize) * 4;
+        &mut self.data[start..end]
+    }
+
+    #[inline]
+    pub fn offset(&self, x: usize, y: usize) -> usize {
+        y * self.stride + x * 4
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+
+    /// Read-only view of the same pixels.
+    pub fn as_image(&self) -> RgbaImage<'_> {
+        RgbaImage { data: self.data, width: self.width, height: self.height, stride: self.stride }
+    }
+
+    pub fn to_packed(&self) -> Vec<u8> {
+        self.as_image().to_packed()
+    }
+}
+
+/// Borrowed read-only 8-bit single-channel image, e.g. a grayscale result or an edge mask.
+/// Same layout rules as [`RgbaImage`] with one byte per pixel.
+#[derive(Clone, Copy)]
+pub struct GrayImage<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl<'a> GrayImage<'a> {
+    /// Tightly packed image (`stride == width`).
+    pub fn new(data: &'a [u8], width: u32, height: u32) -> Result<Self, ImageError> {
+        GrayImage::with_stride(data, width, height, width as usize)
+    }
+
+    pub fn with_stride(
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+        stride: usize
+    ) -> Result<Self, ImageError> {
+        check_len(data.len(), width, height, stride, 1)?;
+        Ok(GrayImage { data, width, height, stride })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    #[inline]
+    pub fn row(&self, y: usize) -> &'a [u8] {
+        let start = y * self.stride;
+        &self.data[start..start + (self.width as usize)]
+    }
+}
+
+/// Borrowed mutable 8-bit single-channel image; the in-place counterpart of [`GrayImage`].
+pub struct GrayImageMut<'a> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl<'a> GrayImageMut<'a> {
+    /// Tightly packed image (`stride == width`).
+    pub fn new(data: &'a mut [u8], width: u32, height: u32) -> Result<Self, ImageError> {
+        GrayImageMut::with_stride(data, width, height, width as usize)
+    }
+
+    pub fn with_stride(
+        data: &'a mut [u8],
+        width: u32,
+        height: u32,
+        stride: usize
+    ) -> Result<Self, ImageError> {
+        check_len(data.len(), width, height, stride, 1)?;
+        Ok(GrayImageMut { data, width, height, stride })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    #[inline]
+    pub fn row(&self, y: usize) -> &[u8] {
+        let start = y * self.stride;
+        &self.data[start..start + (self.width as usize)]
+    }
+
+    #[inline]
+    pub fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        let start = y * self.stride;
+        let end = start + (self.width as usize);
+        &mut self.data[start..end]
+    }
+
+    /// Read-only view of the same pixels.
+    pub fn as_image(&self) -> GrayImage<'_> {
+        GrayImage { data: self.data, width: self.width, height: self.height, stride: self.stride }
+    }
+
+    /// Fails with `InvalidParameter("dst")` unless this image is `width x height`, i.e. the
+    /// same size as the source a filter reads from.
+    pub(crate) fn require_size(&self, width: u32, height: u32) -> Result<(), ImageError> {
+        if self.width != width || self.height != height {
+            return Err(ImageError::InvalidParameter { name: "dst" });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn buffer_length_and_stride_are_checked() {
+        let data = [0u8; 40];
+        assert!(RgbaImage::new(&data, 2, 5).is_ok());
+        assert_eq!(
+            RgbaImage::new(&data, 3, 4).err(),
+            Some(ImageError::BufferTooSmall { needed: 48, available: 40 })
+        );
+        // The last row needs no padding
+        assert!(RgbaImage::with_stride(&data, 2, 4, 10).is_ok());
+        assert_eq!(RgbaImage::with_stride(&data, 2, 2, 7).err(), Some(ImageError::InvalidStride { stride: 7, min: 8 }));
+        assert_eq!(GrayImage::new(&data, 7, 6).err().map(|e| e.kind()), Some(ErrorKind::BufferTooSmall));
+        assert_eq!(RgbaImage::with_stride(&data, 1, 3, usize::MAX).err(), Some(ImageError::Overflow));
+    }
+
+    #[test]
+    fn min_size_and_matching_size() {
+        let data = [0u8; 64];
+        let img = RgbaImage::new(&data, 4, 2).unwrap();
+        assert!(img.require_min_size(2).is_ok());
+        assert_eq!(img.require_min_size(3).err(), Some(ImageError::InvalidDimensions { width: 4, height: 2, min: 3 }));
+
+        let mut gray = [0u8; 8];
+        let dst = GrayImageMut::new(&mut gray, 4, 2).unwrap();
+        assert!(dst.require_size(4, 2).is_ok());
+        assert_eq!(dst.require_size(2, 4).err(), Some(ImageError::InvalidParameter { name: "dst" }));
+        assert_eq!(dst.require_size(2, 4).unwrap_err().kind(), ErrorKind::InvalidParameter);
+    }
+}
@@ -1,9 +1,21 @@
-mod memory;
-mod filters;
+//! Image filters (grayscale, sepia, colour matrices, Gaussian blur, Sobel and Canny edge detection)
+//! on borrowed RGBA8 buffers.
+//!
+//! The filter logic in [`filters`] is plain safe Rust and runs natively. With the default
+//! `wasm` feature, the [`wasm`] module exposes it to JavaScript through `wasm_bindgen`.
+//! The `profiling` feature records per-stage timings of the last filter call.
 
-pub use memory::{alloc, free, get_allocated_memory_mb};
-pub use filters::blur::gaussian_blur;
-pub use filters::edge_sobel::edge_detection_sobel;
-pub use filters::edge_canny::edge_detection_canny;
-pub use filters::grayscale::grayscale;
-pub use filters::sepia::sepia;
+// Filters take raw pointers into wasm linear memory handed out by `alloc`
+#![cfg_attr(feature = "wasm", allow(clippy::not_unsafe_ptr_arg_deref))]
+
+mod error;
+pub mod filters;
+mod image;
+mod profile;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use error::{ErrorKind, ImageError};
+pub use image::{GrayImage, GrayImageMut, RgbaImage, RgbaImageMut};
+#[cfg(feature = "profiling")]
+pub use profile::{last_profile, Profile, StageTiming};
@@ -0,0 +1,139 @@
+//! Per-stage timings of the last filter call, recorded with the `profiling` feature.
+//!
+//! Filters mark the end of each stage on a [`Profiler`]; when it is dropped the stages are
+//! stored as the thread's last profile. A profiler dropped before its first stage, as when a
+//! filter rejects its parameters, records nothing. Without the feature `Profiler` is an empty
+//! struct and every call compiles away.
+
+#[cfg(feature = "profiling")]
+use std::cell::RefCell;
+
+/// Duration of one named stage of a filter.
+#[cfg(feature = "profiling")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StageTiming {
+    pub name: &'static str,
+    pub ms: f64,
+}
+
+/// Timings of one filter call, stages in execution order.
+#[cfg(feature = "profiling")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub filter: &'static str,
+    pub total_ms: f64,
+    pub stages: Vec<StageTiming>,
+}
+
+#[cfg(feature = "profiling")]
+impl Profile {
+    /// `{"filter":"...","total_ms":...,"stages":[{"name":"...","ms":...},...]}`
+    pub fn to_json(&self) -> String {
+        let stages: Vec<String> = self.stages
+            .iter()
+            .map(|s| format!("{{\"name\":\"{}\",\"ms\":{}}}", s.name, s.ms))
+            .collect();
+        format!(
+            "{{\"filter\":\"{}\",\"total_ms\":{},\"stages\":[{}]}}",
+            self.filter,
+            self.total_ms,
+            stages.join(",")
+        )
+    }
+}
+
+#[cfg(feature = "profiling")]
+thread_local! {
+    static LAST_PROFILE: RefCell<Option<Profile>> = const { RefCell::new(None) };
+}
+
+/// Profile of the most recent filter call on this thread, if any filter ran yet.
+#[cfg(feature = "profiling")]
+pub fn last_profile() -> Option<Profile> {
+    LAST_PROFILE.with(|last| last.borrow().clone())
+}
+
+/// Milliseconds from an arbitrary origin: `performance.now()` in the browser.
+#[cfg(all(feature = "profiling", target_arch = "wasm32", feature = "wasm"))]
+fn now_ms() -> f64 {
+    use wasm_bindgen::JsCast;
+
+    thread_local! {
+        // `performance` exists on both `window` and worker scopes
+        static PERFORMANCE: Option<web_sys::Performance> =
+            js_sys::Reflect::get(&js_sys::global(), &"performance".into())
+                .ok()
+                .and_then(|p| p.dyn_into::<web_sys::Performance>().ok());
+    }
+    PERFORMANCE.with(|p| p.as_ref().map_or(0.0, |p| p.now()))
+}
+
+/// Milliseconds from an arbitrary origin: the first call.
+#[cfg(all(feature = "profiling", not(target_arch = "wasm32")))]
+fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    ORIGIN.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+/// No clock on wasm without the JS bindings: stages are recorded with zero duration.
+#[cfg(all(feature = "profiling", target_arch = "wasm32", not(feature = "wasm")))]
+fn now_ms() -> f64 {
+    0.0
+}
+
+/// Stage recorder for one filter call.
+#[cfg(feature = "profiling")]
+pub(crate) struct Profiler {
+    filter: &'static str,
+    start: f64,
+    last: f64,
+    stages: Vec<StageTiming>,
+}
+
+#[cfg(feature = "profiling")]
+impl Profiler {
+    pub(crate) fn start(filter: &'static str) -> Profiler {
+        let now = now_ms();
+        Profiler { filter, start: now, last: now, stages: Vec::new() }
+    }
+
+    /// End the stage called `name`, which began at the previous mark.
+    pub(crate) fn stage(&mut self, name: &'static str) {
+        let now = now_ms();
+        self.stages.push(StageTiming { name, ms: now - self.last });
+        self.last = now;
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        // Failed before doing any work: keep the previous profile
+        if self.stages.is_empty() {
+            return;
+        }
+        let profile = Profile {
+            filter: self.filter,
+            total_ms: now_ms() - self.start,
+            stages: std::mem::take(&mut self.stages),
+        };
+        LAST_PROFILE.with(|last| *last.borrow_mut() = Some(profile));
+    }
+}
+
+#[cfg(not(feature = "profiling"))]
+pub(crate) struct Profiler;
+
+#[cfg(not(feature = "profiling"))]
+impl Profiler {
+    #[inline(always)]
+    pub(crate) fn start(_filter: &'static str) -> Profiler {
+        Profiler
+    }
+
+    #[inline(always)]
+    pub(crate) fn stage(&mut self, _name: &'static str) {}
+}
@@ -0,0 +1,44 @@
+use wasm_bindgen::prelude::*;
+
+use crate::filters::border::BorderMode;
+
+/// JS handle for a [`BorderMode`], e.g. `Border.reflect101()` or `Border.constant(255)`.
+/// Passed by reference, so one instance can be reused across calls.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct Border {
+    mode: BorderMode,
+}
+
+#[wasm_bindgen]
+impl Border {
+    pub fn clamp() -> Border {
+        Border { mode: BorderMode::Clamp }
+    }
+
+    pub fn reflect() -> Border {
+        Border { mode: BorderMode::Reflect }
+    }
+
+    pub fn reflect101() -> Border {
+        Border { mode: BorderMode::Reflect101 }
+    }
+
+    pub fn wrap() -> Border {
+        Border { mode: BorderMode::Wrap }
+    }
+
+    pub fn constant(value: u8) -> Border {
+        Border { mode: BorderMode::Constant(value) }
+    }
+
+    pub fn skip() -> Border {
+        Border { mode: BorderMode::Skip }
+    }
+}
+
+impl Border {
+    pub(crate) fn mode(&self) -> BorderMode {
+        self.mode
+    }
+}
@@ -0,0 +1,136 @@
+use wasm_bindgen::prelude::*;
+
+use crate::filters::edge_canny::{
+    self, CannyBlur, CannyConfig, CannyNms, CannyStage, CannyStrength, CannyThreshold, Stroke,
+};
+use crate::filters::gradient::GradientMode;
+use super::border::Border;
+use super::image_buffer::ImageBuffer;
+
+/// JS handle for a full Canny configuration, e.g.
+/// `Canny.preset(CannyStrength.Low).with_sigma(1.4).with_low_ratio(0.4).with_border(Border.reflect())`.
+/// Invalid values are reported when the filter runs.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct Canny {
+    config: CannyConfig,
+}
+
+impl Canny {
+    pub(crate) fn config(&self) -> &CannyConfig {
+        &self.config
+    }
+
+    fn with(&self, f: impl FnOnce(&mut CannyConfig)) -> Canny {
+        let mut config = self.config;
+        f(&mut config);
+        Canny { config }
+    }
+}
+
+#[wasm_bindgen]
+impl Canny {
+    /// Strength preset with a medium stroke and skipped borders.
+    pub fn preset(strength: CannyStrength) -> Canny {
+        Canny { config: CannyConfig::preset(strength) }
+    }
+
+    /// Gaussian pre-blur of `sigma` (0.1 to 64); 0 disables the blur.
+    pub fn with_sigma(&self, sigma: f32) -> Canny {
+        self.with(|c| c.blur = if sigma == 0.0 { CannyBlur::None } else { CannyBlur::Gaussian(sigma) })
+    }
+
+    /// Binomial pre-blur with an odd number of taps.
+    pub fn with_binomial(&self, taps: usize) -> Canny {
+        self.with(|c| c.blur = CannyBlur::Binomial(taps))
+    }
+
+    pub fn with_high_percentile(&self, p: f32) -> Canny {
+        self.with(|c| c.high = CannyThreshold::Percentile(p))
+    }
+
+    pub fn with_high_fraction_of_max(&self, fraction: f32) -> Canny {
+        self.with(|c| c.high = CannyThreshold::FractionOfMax(fraction))
+    }
+
+    pub fn with_high_absolute(&self, threshold: f32) -> Canny {
+        self.with(|c| c.high = CannyThreshold::Absolute(threshold))
+    }
+
+    pub fn with_high_otsu(&self) -> Canny {
+        self.with(|c| c.high = CannyThreshold::Otsu)
+    }
+
+    pub fn with_low_percentile(&self, p: f32) -> Canny {
+        self.with(|c| c.low = CannyThreshold::Percentile(p))
+    }
+
+    pub fn with_low_fraction_of_max(&self, fraction: f32) -> Canny {
+        self.with(|c| c.low = CannyThreshold::FractionOfMax(fraction))
+    }
+
+    /// Low threshold as a fraction of the high threshold.
+    pub fn with_low_ratio(&self, ratio: f32) -> Canny {
+        self.with(|c| c.low = CannyThreshold::RatioOfHigh(ratio))
+    }
+
+    pub fn with_low_absolute(&self, threshold: f32) -> Canny {
+        self.with(|c| c.low = CannyThreshold::Absolute(threshold))
+    }
+
+    pub fn with_low_otsu(&self) -> Canny {
+        self.with(|c| c.low = CannyThreshold::Otsu)
+    }
+
+    pub fn with_stroke(&self, stroke: Stroke) -> Canny {
+        self.with(|c| c.stroke = stroke)
+    }
+
+    pub fn with_border(&self, border: &Border) -> Canny {
+        self.with(|c| c.border = border.mode())
+    }
+
+    pub fn with_nms(&self, nms: CannyNms) -> Canny {
+        self.with(|c| c.nms = nms)
+    }
+
+    /// Colour gradient, so edges between colours of equal brightness are found too.
+    pub fn with_gradient(&self, gradient: GradientMode) -> Canny {
+        self.with(|c| c.gradient = gradient)
+    }
+
+    /// Sub-pixel refinement of `canny_edge_points` and `canny_contours`.
+    pub fn with_subpixel(&self, subpixel: bool) -> Canny {
+        self.with(|c| c.subpixel = subpixel)
+    }
+}
+
+/// Every stage of one Canny run, see `canny_debug`. Each call to `stage()` renders that
+/// stage into its own `ImageBuffer`, so they can be shown side by side.
+#[wasm_bindgen]
+pub struct CannyDebug {
+    inner: edge_canny::CannyDebug,
+}
+
+impl From<edge_canny::CannyDebug> for CannyDebug {
+    fn from(inner: edge_canny::CannyDebug) -> CannyDebug {
+        CannyDebug { inner }
+    }
+}
+
+#[wasm_bindgen]
+impl CannyDebug {
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.inner.width()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.inner.height()
+    }
+
+    pub fn stage(&self, stage: CannyStage) -> ImageBuffer {
+        ImageBuffer::with_data(self.inner.stage(stage), self.inner.width(), self.inner.height())
+    }
+}
@@ -0,0 +1,73 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::ImageError;
+use crate::filters::color_matrix;
+
+/// JS handle for a 4x5 colour matrix, e.g. `ColorMatrix.sepia().with_intensity(0.6)` or
+/// `ColorMatrix.from_values(new Float32Array([...20 values]))`.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct ColorMatrix {
+    inner: color_matrix::ColorMatrix,
+}
+
+impl ColorMatrix {
+    pub(crate) fn matrix(&self) -> &color_matrix::ColorMatrix {
+        &self.inner
+    }
+}
+
+impl From<color_matrix::ColorMatrix> for ColorMatrix {
+    fn from(inner: color_matrix::ColorMatrix) -> ColorMatrix {
+        ColorMatrix { inner }
+    }
+}
+
+/// Check a JS array of 20 row-major values.
+pub(crate) fn matrix_from_slice(values: &[f32]) -> Result<color_matrix::ColorMatrix, ImageError> {
+    let values: [f32; 20] = values.try_into().map_err(|_| ImageError::InvalidParameter { name: "matrix" })?;
+    color_matrix::ColorMatrix::new(values)
+}
+
+#[wasm_bindgen]
+impl ColorMatrix {
+    /// 20 values, row-major, offsets in 0..1 units as in SVG `feColorMatrix`.
+    pub fn from_values(values: &[f32]) -> Result<ColorMatrix, JsValue> {
+        Ok(matrix_from_slice(values)?.into())
+    }
+
+    pub fn identity() -> ColorMatrix {
+        color_matrix::ColorMatrix::IDENTITY.into()
+    }
+
+    pub fn sepia() -> ColorMatrix {
+        color_matrix::ColorMatrix::sepia().into()
+    }
+
+    pub fn grayscale() -> ColorMatrix {
+        color_matrix::ColorMatrix::grayscale().into()
+    }
+
+    pub fn invert() -> ColorMatrix {
+        color_matrix::ColorMatrix::invert().into()
+    }
+
+    /// 0 is grayscale, 1 unchanged, above 1 oversaturated.
+    pub fn saturate(s: f32) -> Result<ColorMatrix, JsValue> {
+        Ok(color_matrix::ColorMatrix::saturate(s)?.into())
+    }
+
+    pub fn hue_rotate(degrees: f32) -> Result<ColorMatrix, JsValue> {
+        Ok(color_matrix::ColorMatrix::hue_rotate(degrees)?.into())
+    }
+
+    /// Blend with the identity: 0 leaves the image unchanged, 1 applies the full matrix.
+    pub fn with_intensity(&self, intensity: f32) -> Result<ColorMatrix, JsValue> {
+        Ok(self.inner.with_intensity(intensity)?.into())
+    }
+
+    /// The 20 values, row-major.
+    pub fn values(&self) -> Vec<f32> {
+        self.inner.values().to_vec()
+    }
+}
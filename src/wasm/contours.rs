@@ -0,0 +1,50 @@
+use wasm_bindgen::prelude::*;
+
+use crate::filters::contour;
+use crate::filters::svg::{self, SvgStyle};
+
+/// Edge chains as flat arrays, e.g. for drawing with `Path2D`:
+/// chain `i` is points `offsets[i]..offsets[i + 1]` of `points` (`x, y` interleaved).
+#[wasm_bindgen]
+pub struct Contours {
+    inner: contour::Contours,
+}
+
+impl From<contour::Contours> for Contours {
+    fn from(inner: contour::Contours) -> Contours {
+        Contours { inner }
+    }
+}
+
+#[wasm_bindgen]
+impl Contours {
+    /// Number of chains.
+    #[wasm_bindgen(getter)]
+    pub fn count(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// All points as `Float32Array` `[x0, y0, x1, y1, ...]`, at pixel centres unless the
+    /// contours came from a `Canny` with `with_subpixel(true)`.
+    pub fn points(&self) -> Vec<f32> {
+        self.inner.points().to_vec()
+    }
+
+    /// `Uint32Array` of `count + 1` point indices delimiting the chains.
+    pub fn offsets(&self) -> Vec<u32> {
+        self.inner.offsets().to_vec()
+    }
+
+    /// SVG document with one path per chain, e.g. `contours.to_svg("#e91e63", 1.5)`.
+    pub fn to_svg(&self, stroke: String, stroke_width: f32) -> Result<String, JsValue> {
+        Ok(svg::contours_to_svg(&self.inner, &SvgStyle { stroke, stroke_width })?)
+    }
+}
+
+/// Apply the optional Douglas-Peucker `epsilon` shared by the contour exports.
+pub(crate) fn simplified(contours: contour::Contours, epsilon: Option<f32>) -> Result<Contours, JsValue> {
+    Ok(match epsilon {
+        Some(epsilon) => contours.simplify(epsilon)?.into(),
+        None => contours.into(),
+    })
+}
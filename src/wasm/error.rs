@@ -0,0 +1,25 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{ErrorKind, ImageError};
+
+/// JS `Error` with `message`, a numeric `code` from `ErrorKind` and, for invalid parameters,
+/// the parameter name as `parameter`.
+pub(crate) fn coded_error(message: &str, kind: ErrorKind, parameter: Option<&str>) -> JsValue {
+    let error = js_sys::Error::new(message);
+    // Setting a property on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&error, &"code".into(), &(kind as u32).into());
+    if let Some(parameter) = parameter {
+        let _ = js_sys::Reflect::set(&error, &"parameter".into(), &parameter.into());
+    }
+    error.into()
+}
+
+impl From<ImageError> for JsValue {
+    fn from(error: ImageError) -> JsValue {
+        let parameter = match error {
+            ImageError::InvalidParameter { name } => Some(name),
+            _ => None,
+        };
+        coded_error(&error.to_string(), error.kind(), parameter)
+    }
+}
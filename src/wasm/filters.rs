@@ -0,0 +1,503 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::ImageError;
+use crate::filters;
+use crate::filters::blur::BlurAlgorithm;
+use crate::filters::border::BorderMode;
+use crate::filters::curves::Curves;
+use crate::filters::edge_canny::{CannyStrength, Stroke};
+use crate::filters::grayscale::GrayscaleMethod;
+use crate::filters::lut::Lut;
+use crate::filters::svg::SvgStyle;
+use crate::image::{GrayImage, GrayImageMut, RgbaImage, RgbaImageMut};
+use super::border::Border;
+use super::canny::{Canny, CannyDebug};
+use super::color_matrix::{matrix_from_slice, ColorMatrix};
+use super::contours::{simplified, Contours};
+use super::memory::{gray_slice_mut, rgba_len, rgba_slice_mut};
+use super::sobel::Sobel;
+
+/// Wrap a pointer from `alloc` as a core image after validating it.
+fn image_from_ptr<'a>(ptr: *mut u8, width: u32, height: u32) -> Result<RgbaImageMut<'a>, ImageError> {
+    let data = rgba_slice_mut(ptr, width, height)?;
+    RgbaImageMut::new(data, width, height)
+}
+
+/// Validate an RGBA source and a one-byte-per-pixel destination of the same size. The two
+/// buffers must not overlap, since the filter reads the source while writing the destination.
+fn rgba_to_gray_from_ptrs<'a>(
+    src_ptr: *mut u8,
+    dst_ptr: *mut u8,
+    width: u32,
+    height: u32
+) -> Result<(RgbaImage<'a>, GrayImageMut<'a>), ImageError> {
+    let gray_len = (width as usize).checked_mul(height as usize).ok_or(ImageError::Overflow)?;
+    check_disjoint(src_ptr, rgba_len(width, height)?, dst_ptr, gray_len)?;
+    let src = RgbaImage::new(rgba_slice_mut(src_ptr, width, height)?, width, height)?;
+    let dst = GrayImageMut::new(gray_slice_mut(dst_ptr, width, height)?, width, height)?;
+    Ok((src, dst))
+}
+
+fn check_disjoint(a: *const u8, a_len: usize, b: *const u8, b_len: usize) -> Result<(), ImageError> {
+    let (a, b) = (a as usize, b as usize);
+    if a < b.saturating_add(b_len) && b < a.saturating_add(a_len) {
+        return Err(ImageError::InvalidParameter { name: "dst" });
+    }
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn grayscale(ptr: *mut u8, width: u32, height: u32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::grayscale::grayscale(&mut img);
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn grayscale_method(ptr: *mut u8, width: u32, height: u32, method: GrayscaleMethod) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::grayscale::grayscale_with(&mut img, method);
+    Ok(())
+}
+
+/// Gray values of the RGBA image at `src_ptr` written one byte per pixel to `dst_ptr`
+/// (`width * height` bytes). The source is not modified.
+#[wasm_bindgen]
+pub fn grayscale_into(
+    src_ptr: *mut u8,
+    dst_ptr: *mut u8,
+    width: u32,
+    height: u32,
+    method: GrayscaleMethod
+) -> Result<(), JsValue> {
+    let (src, mut dst) = rgba_to_gray_from_ptrs(src_ptr, dst_ptr, width, height)?;
+    filters::grayscale::grayscale_into(src, &mut dst, method)?;
+    Ok(())
+}
+
+/// Copy a one-byte-per-pixel image at `src_ptr` into the RGBA buffer at `dst_ptr` for display.
+#[wasm_bindgen]
+pub fn expand_gray_to_rgba(src_ptr: *mut u8, dst_ptr: *mut u8, width: u32, height: u32) -> Result<(), JsValue> {
+    let gray_len = (width as usize).checked_mul(height as usize).ok_or(ImageError::Overflow)?;
+    check_disjoint(src_ptr, gray_len, dst_ptr, rgba_len(width, height)?)?;
+    let src = GrayImage::new(gray_slice_mut(src_ptr, width, height)?, width, height)?;
+    let mut dst = image_from_ptr(dst_ptr, width, height)?;
+    filters::grayscale::expand_gray_to_rgba(src, &mut dst)?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn sepia(ptr: *mut u8, width: u32, height: u32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::sepia::sepia(&mut img);
+    Ok(())
+}
+
+/// Sepia blended with the original by `intensity` (0..=1).
+#[wasm_bindgen]
+pub fn sepia_intensity(ptr: *mut u8, width: u32, height: u32, intensity: f32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::sepia::sepia_with(&mut img, intensity)?;
+    Ok(())
+}
+
+/// Apply a 4x5 colour matrix given as 20 row-major values (`feColorMatrix` layout).
+#[wasm_bindgen]
+pub fn color_matrix(ptr: *mut u8, width: u32, height: u32, matrix: &[f32]) -> Result<(), JsValue> {
+    let matrix = matrix_from_slice(matrix)?;
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::color_matrix::color_matrix(&mut img, &matrix);
+    Ok(())
+}
+
+/// Apply a preset such as `ColorMatrix.hue_rotate(90).with_intensity(0.5)`.
+#[wasm_bindgen]
+pub fn color_matrix_with(ptr: *mut u8, width: u32, height: u32, matrix: &ColorMatrix) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::color_matrix::color_matrix(&mut img, matrix.matrix());
+    Ok(())
+}
+
+/// Shift R, G and B by `amount` (-1..=1) of the full range.
+#[wasm_bindgen]
+pub fn brightness(ptr: *mut u8, width: u32, height: u32, amount: f32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::tone::brightness(&mut img, amount)?;
+    Ok(())
+}
+
+/// Scale R, G and B around mid-gray: 0 is flat gray, 1 unchanged.
+#[wasm_bindgen]
+pub fn contrast(ptr: *mut u8, width: u32, height: u32, factor: f32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::tone::contrast(&mut img, factor)?;
+    Ok(())
+}
+
+/// Gamma correction; above 1 brightens the midtones.
+#[wasm_bindgen]
+pub fn gamma(ptr: *mut u8, width: u32, height: u32, gamma: f32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::tone::gamma(&mut img, gamma)?;
+    Ok(())
+}
+
+/// Exposure change in stops, applied in linear light.
+#[wasm_bindgen]
+pub fn exposure(ptr: *mut u8, width: u32, height: u32, stops: f32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::tone::exposure(&mut img, stops)?;
+    Ok(())
+}
+
+/// Input levels: `black` and `white` points and the `midtone` gamma.
+#[wasm_bindgen]
+pub fn levels(ptr: *mut u8, width: u32, height: u32, black: u8, white: u8, midtone: f32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::tone::levels(&mut img, black, white, midtone)?;
+    Ok(())
+}
+
+/// Apply four 256-entry `Uint8Array` lookup tables, one per channel.
+#[wasm_bindgen]
+pub fn apply_lut(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    lut_r: &[u8],
+    lut_g: &[u8],
+    lut_b: &[u8],
+    lut_a: &[u8]
+) -> Result<(), JsValue> {
+    let lut = lut_from_slices(lut_r, lut_g, lut_b, lut_a)?;
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::lut::apply_lut(&mut img, &lut);
+    Ok(())
+}
+
+/// Check four JS tables of 256 entries each.
+pub(crate) fn lut_from_slices(r: &[u8], g: &[u8], b: &[u8], a: &[u8]) -> Result<Lut, ImageError> {
+    let table_from_slice = |values: &[u8], name: &'static str| -> Result<[u8; 256], ImageError> {
+        values.try_into().map_err(|_| ImageError::InvalidParameter { name })
+    };
+    Ok(Lut::new(
+        table_from_slice(r, "lut_r")?,
+        table_from_slice(g, "lut_g")?,
+        table_from_slice(b, "lut_b")?,
+        table_from_slice(a, "lut_a")?
+    ))
+}
+
+/// Photoshop-style curves. Each argument is a `Float32Array` of control points
+/// `[x0, y0, x1, y1, ...]` in 0..=255; pass an empty array to leave that curve straight.
+#[wasm_bindgen]
+pub fn curves(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    master: &[f32],
+    red: &[f32],
+    green: &[f32],
+    blue: &[f32]
+) -> Result<(), JsValue> {
+    let curves = curves_from_slices(master, red, green, blue)?;
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::curves::curves(&mut img, &curves)?;
+    Ok(())
+}
+
+/// Check four flat `x, y` point lists.
+pub(crate) fn curves_from_slices(
+    master: &[f32],
+    red: &[f32],
+    green: &[f32],
+    blue: &[f32]
+) -> Result<Curves, ImageError> {
+    let points = |flat: &[f32], name: &'static str| -> Result<Vec<(f32, f32)>, ImageError> {
+        if !flat.len().is_multiple_of(2) {
+            return Err(ImageError::InvalidParameter { name });
+        }
+        Ok(flat.chunks_exact(2).map(|p| (p[0], p[1])).collect())
+    };
+    Ok(Curves {
+        master: points(master, "master")?,
+        red: points(red, "red")?,
+        green: points(green, "green")?,
+        blue: points(blue, "blue")?,
+    })
+}
+
+/// Binarize on luma: white above `level`, black elsewhere.
+#[wasm_bindgen]
+pub fn threshold_fixed(ptr: *mut u8, width: u32, height: u32, level: u8) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::threshold::threshold_fixed(&mut img, level);
+    Ok(())
+}
+
+/// Binarize at the Otsu level of the luma histogram; returns that level.
+#[wasm_bindgen]
+pub fn threshold_otsu(ptr: *mut u8, width: u32, height: u32) -> Result<u8, JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    Ok(filters::threshold::threshold_otsu(&mut img))
+}
+
+/// Posterize into `classes` grays at the multi-level Otsu levels; returns the levels.
+#[wasm_bindgen]
+pub fn threshold_otsu_multi(ptr: *mut u8, width: u32, height: u32, classes: usize) -> Result<Vec<u8>, JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    Ok(filters::threshold::threshold_otsu_multi(&mut img, classes)?)
+}
+
+#[wasm_bindgen]
+pub fn gaussian_blur(ptr: *mut u8, width: u32, height: u32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::blur::gaussian_blur(&mut img);
+    Ok(())
+}
+
+/// Blur with an adjustable `sigma`; `radius` defaults to `ceil(3 * sigma)` when omitted.
+#[wasm_bindgen]
+pub fn gaussian_blur_with(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    sigma: f64,
+    radius: Option<u32>
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::blur::gaussian_blur_with(&mut img, sigma, radius.map(|r| r as usize))?;
+    Ok(())
+}
+
+/// Blur using the chosen `BlurAlgorithm`, for comparing implementations at the same `sigma`.
+#[wasm_bindgen]
+pub fn gaussian_blur_algorithm(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    sigma: f64,
+    radius: Option<u32>,
+    algorithm: BlurAlgorithm
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::blur::gaussian_blur_with_algorithm(
+        &mut img,
+        sigma,
+        radius.map(|r| r as usize),
+        algorithm,
+        BorderMode::Clamp
+    )?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn gaussian_blur_border(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    sigma: f64,
+    radius: Option<u32>,
+    algorithm: BlurAlgorithm,
+    border: &Border
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::blur::gaussian_blur_with_algorithm(
+        &mut img,
+        sigma,
+        radius.map(|r| r as usize),
+        algorithm,
+        border.mode()
+    )?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn edge_detection_sobel(ptr: *mut u8, width: u32, height: u32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_sobel::edge_detection_sobel(&mut img)?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn edge_detection_sobel_border(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    border: &Border
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_sobel::edge_detection_sobel_with_border(&mut img, border.mode())?;
+    Ok(())
+}
+
+/// Sobel with a selectable output: `Sobel.percentile(p)`, `.absolute(t)`, `.otsu()` or `.magnitude()`.
+#[wasm_bindgen]
+pub fn edge_detection_sobel_with(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    mode: &Sobel
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_sobel::edge_detection_sobel_with(&mut img, mode.mode(), mode.border(), mode.gradient())?;
+    Ok(())
+}
+
+/// Sobel edge map of the RGBA image at `src_ptr`, one byte per pixel at `dst_ptr`.
+#[wasm_bindgen]
+pub fn edge_detection_sobel_into(
+    src_ptr: *mut u8,
+    dst_ptr: *mut u8,
+    width: u32,
+    height: u32,
+    mode: &Sobel
+) -> Result<(), JsValue> {
+    let (src, mut dst) = rgba_to_gray_from_ptrs(src_ptr, dst_ptr, width, height)?;
+    filters::edge_sobel::edge_detection_sobel_into(src, &mut dst, mode.mode(), mode.border(), mode.gradient())?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn edge_detection_canny(ptr: *mut u8, width: u32, height: u32) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_canny::edge_detection_canny(&mut img)?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn edge_detection_canny_cfg(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    high_percentile: f32,
+    low_ratio: f32,
+    sigma: f32
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_canny::edge_detection_canny_cfg(&mut img, high_percentile, low_ratio, sigma)?;
+    Ok(())
+}
+
+/// Canny preset with a chosen stroke.
+#[wasm_bindgen]
+pub fn edge_detection_canny_preset(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    strength: CannyStrength,
+    stroke: Stroke
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_canny::edge_detection_canny_strength_stroke(&mut img, strength, stroke)?;
+    Ok(())
+}
+
+/// String form kept for existing callers; unknown values are an error.
+///
+/// @deprecated Use edge_detection_canny_preset with CannyStrength.
+#[wasm_bindgen]
+pub fn edge_detection_canny_strength(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    strength: String
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_canny::edge_detection_canny_strength(&mut img, strength.parse()?)?;
+    Ok(())
+}
+
+/// String form kept for existing callers; unknown values are an error.
+///
+/// @deprecated Use edge_detection_canny_preset with CannyStrength and Stroke.
+#[wasm_bindgen]
+pub fn edge_detection_canny_strength_stroke(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    strength: String,
+    stroke: String
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_canny::edge_detection_canny_strength_stroke(&mut img, strength.parse()?, stroke.parse()?)?;
+    Ok(())
+}
+
+/// Canny preset with a border mode; see `edge_detection_canny_with` for full control.
+#[wasm_bindgen]
+pub fn edge_detection_canny_border(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    strength: CannyStrength,
+    stroke: Stroke,
+    border: &Border
+) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_canny::edge_detection_canny_border(&mut img, strength, stroke, border.mode())?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn edge_detection_canny_with(ptr: *mut u8, width: u32, height: u32, config: &Canny) -> Result<(), JsValue> {
+    let mut img = image_from_ptr(ptr, width, height)?;
+    filters::edge_canny::edge_detection_canny_with(&mut img, config.config())?;
+    Ok(())
+}
+
+/// Canny edge mask (0 or 255) of the RGBA image at `src_ptr`, one byte per pixel at `dst_ptr`.
+#[wasm_bindgen]
+pub fn edge_detection_canny_into(
+    src_ptr: *mut u8,
+    dst_ptr: *mut u8,
+    width: u32,
+    height: u32,
+    config: &Canny
+) -> Result<(), JsValue> {
+    let (src, mut dst) = rgba_to_gray_from_ptrs(src_ptr, dst_ptr, width, height)?;
+    filters::edge_canny::edge_detection_canny_into(src, &mut dst, config.config())?;
+    Ok(())
+}
+
+/// Canny stage by stage for inspection; the image at `ptr` is not modified.
+#[wasm_bindgen]
+pub fn canny_debug(ptr: *mut u8, width: u32, height: u32, config: &Canny) -> Result<CannyDebug, JsValue> {
+    let img = image_from_ptr(ptr, width, height)?;
+    Ok(filters::edge_canny::canny_debug(img.as_image(), config.config())?.into())
+}
+
+/// Canny edges traced into point chains, simplified with Douglas-Peucker when `epsilon` is given.
+#[wasm_bindgen]
+pub fn canny_contours(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    config: &Canny,
+    epsilon: Option<f32>
+) -> Result<Contours, JsValue> {
+    let img = image_from_ptr(ptr, width, height)?;
+    simplified(filters::edge_canny::canny_contours(img.as_image(), config.config())?, epsilon)
+}
+
+/// Trace an edge map (e.g. after `edge_detection_sobel`) into an SVG document string.
+#[wasm_bindgen]
+pub fn edge_mask_svg(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    epsilon: Option<f32>,
+    stroke: String,
+    stroke_width: f32
+) -> Result<String, JsValue> {
+    let img = image_from_ptr(ptr, width, height)?;
+    let style = SvgStyle { stroke, stroke_width };
+    Ok(filters::svg::edge_mask_to_svg(img.as_image(), epsilon, &style)?)
+}
+
+/// Canny edge locations as `Float32Array` `[x0, y0, x1, y1, ...]`, sub-pixel with `with_subpixel(true)`.
+#[wasm_bindgen]
+pub fn canny_edge_points(ptr: *mut u8, width: u32, height: u32, config: &Canny) -> Result<Vec<f32>, JsValue> {
+    let img = image_from_ptr(ptr, width, height)?;
+    Ok(filters::edge_canny::canny_edge_points(img.as_image(), config.config())?)
+}
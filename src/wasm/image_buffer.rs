@@ -0,0 +1,394 @@
+use wasm_bindgen::prelude::*;
+
+use crate::filters;
+use crate::filters::blur::BlurAlgorithm;
+use crate::filters::border::BorderMode;
+use crate::filters::edge_canny::{CannyStrength, Stroke};
+use crate::filters::grayscale::GrayscaleMethod;
+use crate::filters::svg::SvgStyle;
+use crate::error::{ErrorKind, ImageError};
+use crate::image::{GrayImage, GrayImageMut, RgbaImage, RgbaImageMut};
+use super::border::Border;
+use super::canny::{Canny, CannyDebug};
+use super::color_matrix::{matrix_from_slice, ColorMatrix};
+use super::contours::{simplified, Contours};
+use super::error::coded_error;
+use super::filters::{curves_from_slices, lut_from_slices};
+use super::memory::{self, Owner};
+use super::sobel::Sobel;
+
+/// RGBA image whose storage is owned by the WASM module.
+///
+/// Replaces the `alloc` -> write -> filter(ptr) -> read -> `free(ptr, size)` dance:
+/// copy pixels in once, run filters as methods, and read the result through `view()`.
+/// The storage is released (and the accounting updated) when the JS object is freed.
+#[wasm_bindgen]
+pub struct ImageBuffer {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl ImageBuffer {
+    pub(crate) fn with_data(data: Vec<u8>, width: u32, height: u32) -> ImageBuffer {
+        memory::record_alloc(data.as_ptr(), data.len(), Owner::ImageBuffer);
+        ImageBuffer { data, width, height }
+    }
+
+    /// Borrow the pixels as a core image, e.g. to run a filter that has no method here.
+    pub fn as_image_mut(&mut self) -> RgbaImageMut<'_> {
+        RgbaImageMut::new(&mut self.data, self.width, self.height)
+            .expect("ImageBuffer storage always matches its dimensions")
+    }
+
+    /// Run `f` from these pixels into a fresh `width * height` single-channel buffer.
+    fn to_gray8(
+        &self,
+        f: impl FnOnce(RgbaImage, &mut GrayImageMut) -> Result<(), ImageError>
+    ) -> Result<Vec<u8>, JsValue> {
+        let img = RgbaImage::new(&self.data, self.width, self.height)?;
+        let mut out = vec![0u8; (self.width as usize) * (self.height as usize)];
+        f(img, &mut GrayImageMut::new(&mut out, self.width, self.height)?)?;
+        Ok(out)
+    }
+}
+
+/// Input array of the wrong length for the image.
+fn data_length_error(message: String) -> JsValue {
+    coded_error(&message, ErrorKind::InvalidParameter, Some("data"))
+}
+
+#[wasm_bindgen]
+impl ImageBuffer {
+    /// Allocate a zeroed (transparent black) image.
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u32, height: u32) -> Result<ImageBuffer, JsValue> {
+        let len = memory::rgba_len(width, height)?;
+        Ok(ImageBuffer::with_data(vec![0u8; len], width, height))
+    }
+
+    /// Copy RGBA pixels (e.g. `ImageData.data`) into a new buffer.
+    pub fn from_rgba(data: &[u8], width: u32, height: u32) -> Result<ImageBuffer, JsValue> {
+        let len = memory::rgba_len(width, height)?;
+        if data.len() != len {
+            return Err(data_length_error(format!(
+                "expected {} bytes for {}x{} RGBA, got {}", len, width, height, data.len()
+            )));
+        }
+        Ok(ImageBuffer::with_data(data.to_vec(), width, height))
+    }
+
+    /// Expand a one-byte-per-pixel image (e.g. from `grayscale_gray8`) into a new opaque buffer.
+    pub fn from_gray8(data: &[u8], width: u32, height: u32) -> Result<ImageBuffer, JsValue> {
+        let len = (width as usize).checked_mul(height as usize).ok_or(ImageError::Overflow)?;
+        if data.len() != len {
+            return Err(data_length_error(format!(
+                "expected {} bytes for {}x{} gray, got {}", len, width, height, data.len()
+            )));
+        }
+        let gray = GrayImage::new(data, width, height)?;
+        let mut buffer = ImageBuffer::new(width, height)?;
+        filters::grayscale::expand_gray_to_rgba(gray, &mut buffer.as_image_mut())?;
+        Ok(buffer)
+    }
+
+    /// Overwrite the pixels with `data`, which must have exactly `len()` bytes.
+    pub fn copy_from(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        if data.len() != self.data.len() {
+            return Err(data_length_error(format!(
+                "expected {} bytes, got {}", self.data.len(), data.len()
+            )));
+        }
+        self.data.copy_from_slice(data);
+        Ok(())
+    }
+
+    pub fn ptr(&self) -> *const u8 {
+        self.data.as_ptr()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Zero-copy view into WASM memory, suitable for `new ImageData(view, w, h)`.
+    ///
+    /// The view is invalidated when the WASM memory grows (any allocation may do this)
+    /// or when the buffer is freed, so create it right before use and do not keep it around.
+    pub fn view(&self) -> js_sys::Uint8ClampedArray {
+        unsafe { js_sys::Uint8ClampedArray::view(&self.data) }
+    }
+
+    /// Copy of the pixels as a JS-owned array that stays valid after the buffer is freed.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    pub fn grayscale(&mut self) {
+        filters::grayscale::grayscale(&mut self.as_image_mut());
+    }
+
+    pub fn grayscale_method(&mut self, method: GrayscaleMethod) {
+        filters::grayscale::grayscale_with(&mut self.as_image_mut(), method);
+    }
+
+    /// Gray values as one byte per pixel; this buffer is left unchanged.
+    pub fn grayscale_gray8(&self, method: GrayscaleMethod) -> Result<Vec<u8>, JsValue> {
+        self.to_gray8(|src, dst| filters::grayscale::grayscale_into(src, dst, method))
+    }
+
+    pub fn sepia(&mut self) {
+        filters::sepia::sepia(&mut self.as_image_mut());
+    }
+
+    pub fn sepia_intensity(&mut self, intensity: f32) -> Result<(), JsValue> {
+        filters::sepia::sepia_with(&mut self.as_image_mut(), intensity)?;
+        Ok(())
+    }
+
+    pub fn color_matrix(&mut self, matrix: &[f32]) -> Result<(), JsValue> {
+        filters::color_matrix::color_matrix(&mut self.as_image_mut(), &matrix_from_slice(matrix)?);
+        Ok(())
+    }
+
+    pub fn color_matrix_with(&mut self, matrix: &ColorMatrix) {
+        filters::color_matrix::color_matrix(&mut self.as_image_mut(), matrix.matrix());
+    }
+
+    pub fn brightness(&mut self, amount: f32) -> Result<(), JsValue> {
+        filters::tone::brightness(&mut self.as_image_mut(), amount)?;
+        Ok(())
+    }
+
+    pub fn contrast(&mut self, factor: f32) -> Result<(), JsValue> {
+        filters::tone::contrast(&mut self.as_image_mut(), factor)?;
+        Ok(())
+    }
+
+    pub fn gamma(&mut self, gamma: f32) -> Result<(), JsValue> {
+        filters::tone::gamma(&mut self.as_image_mut(), gamma)?;
+        Ok(())
+    }
+
+    pub fn exposure(&mut self, stops: f32) -> Result<(), JsValue> {
+        filters::tone::exposure(&mut self.as_image_mut(), stops)?;
+        Ok(())
+    }
+
+    pub fn levels(&mut self, black: u8, white: u8, midtone: f32) -> Result<(), JsValue> {
+        filters::tone::levels(&mut self.as_image_mut(), black, white, midtone)?;
+        Ok(())
+    }
+
+    pub fn apply_lut(&mut self, lut_r: &[u8], lut_g: &[u8], lut_b: &[u8], lut_a: &[u8]) -> Result<(), JsValue> {
+        filters::lut::apply_lut(&mut self.as_image_mut(), &lut_from_slices(lut_r, lut_g, lut_b, lut_a)?);
+        Ok(())
+    }
+
+    pub fn curves(&mut self, master: &[f32], red: &[f32], green: &[f32], blue: &[f32]) -> Result<(), JsValue> {
+        filters::curves::curves(&mut self.as_image_mut(), &curves_from_slices(master, red, green, blue)?)?;
+        Ok(())
+    }
+
+    pub fn threshold_fixed(&mut self, level: u8) {
+        filters::threshold::threshold_fixed(&mut self.as_image_mut(), level);
+    }
+
+    pub fn threshold_otsu(&mut self) -> u8 {
+        filters::threshold::threshold_otsu(&mut self.as_image_mut())
+    }
+
+    pub fn threshold_otsu_multi(&mut self, classes: usize) -> Result<Vec<u8>, JsValue> {
+        Ok(filters::threshold::threshold_otsu_multi(&mut self.as_image_mut(), classes)?)
+    }
+
+    pub fn gaussian_blur(&mut self) {
+        filters::blur::gaussian_blur(&mut self.as_image_mut());
+    }
+
+    pub fn gaussian_blur_with(&mut self, sigma: f64, radius: Option<u32>) -> Result<(), JsValue> {
+        filters::blur::gaussian_blur_with(&mut self.as_image_mut(), sigma, radius.map(|r| r as usize))?;
+        Ok(())
+    }
+
+    pub fn gaussian_blur_algorithm(
+        &mut self,
+        sigma: f64,
+        radius: Option<u32>,
+        algorithm: BlurAlgorithm
+    ) -> Result<(), JsValue> {
+        filters::blur::gaussian_blur_with_algorithm(
+            &mut self.as_image_mut(),
+            sigma,
+            radius.map(|r| r as usize),
+            algorithm,
+            BorderMode::Clamp
+        )?;
+        Ok(())
+    }
+
+    pub fn gaussian_blur_border(
+        &mut self,
+        sigma: f64,
+        radius: Option<u32>,
+        algorithm: BlurAlgorithm,
+        border: &Border
+    ) -> Result<(), JsValue> {
+        filters::blur::gaussian_blur_with_algorithm(
+            &mut self.as_image_mut(),
+            sigma,
+            radius.map(|r| r as usize),
+            algorithm,
+            border.mode()
+        )?;
+        Ok(())
+    }
+
+    pub fn edge_detection_sobel(&mut self) -> Result<(), JsValue> {
+        filters::edge_sobel::edge_detection_sobel(&mut self.as_image_mut())?;
+        Ok(())
+    }
+
+    pub fn edge_detection_sobel_border(&mut self, border: &Border) -> Result<(), JsValue> {
+        filters::edge_sobel::edge_detection_sobel_with_border(&mut self.as_image_mut(), border.mode())?;
+        Ok(())
+    }
+
+    pub fn edge_detection_sobel_with(&mut self, mode: &Sobel) -> Result<(), JsValue> {
+        filters::edge_sobel::edge_detection_sobel_with(
+            &mut self.as_image_mut(),
+            mode.mode(),
+            mode.border(),
+            mode.gradient()
+        )?;
+        Ok(())
+    }
+
+    /// Sobel edge map as one byte per pixel; this buffer is left unchanged.
+    pub fn edge_detection_sobel_gray8(&self, mode: &Sobel) -> Result<Vec<u8>, JsValue> {
+        self.to_gray8(|src, dst| {
+            filters::edge_sobel::edge_detection_sobel_into(src, dst, mode.mode(), mode.border(), mode.gradient())
+        })
+    }
+
+    pub fn edge_detection_canny(&mut self) -> Result<(), JsValue> {
+        filters::edge_canny::edge_detection_canny(&mut self.as_image_mut())?;
+        Ok(())
+    }
+
+    pub fn edge_detection_canny_preset(&mut self, strength: CannyStrength, stroke: Stroke) -> Result<(), JsValue> {
+        filters::edge_canny::edge_detection_canny_strength_stroke(&mut self.as_image_mut(), strength, stroke)?;
+        Ok(())
+    }
+
+    /// @deprecated Use edge_detection_canny_preset with CannyStrength.
+    pub fn edge_detection_canny_strength(&mut self, strength: String) -> Result<(), JsValue> {
+        filters::edge_canny::edge_detection_canny_strength(&mut self.as_image_mut(), strength.parse()?)?;
+        Ok(())
+    }
+
+    /// @deprecated Use edge_detection_canny_preset with CannyStrength and Stroke.
+    pub fn edge_detection_canny_strength_stroke(
+        &mut self,
+        strength: String,
+        stroke: String
+    ) -> Result<(), JsValue> {
+        filters::edge_canny::edge_detection_canny_strength_stroke(
+            &mut self.as_image_mut(),
+            strength.parse()?,
+            stroke.parse()?
+        )?;
+        Ok(())
+    }
+
+    pub fn edge_detection_canny_border(
+        &mut self,
+        strength: CannyStrength,
+        stroke: Stroke,
+        border: &Border
+    ) -> Result<(), JsValue> {
+        filters::edge_canny::edge_detection_canny_border(&mut self.as_image_mut(), strength, stroke, border.mode())?;
+        Ok(())
+    }
+
+    pub fn edge_detection_canny_with(&mut self, config: &Canny) -> Result<(), JsValue> {
+        filters::edge_canny::edge_detection_canny_with(&mut self.as_image_mut(), config.config())?;
+        Ok(())
+    }
+
+    /// Canny edge mask (0 or 255) as one byte per pixel; this buffer is left unchanged.
+    pub fn edge_detection_canny_gray8(&self, config: &Canny) -> Result<Vec<u8>, JsValue> {
+        self.to_gray8(|src, dst| filters::edge_canny::edge_detection_canny_into(src, dst, config.config()))
+    }
+
+    /// Run Canny on a copy and return every stage; this buffer is left unchanged.
+    pub fn canny_debug(&self, config: &Canny) -> Result<CannyDebug, JsValue> {
+        let img = RgbaImage::new(&self.data, self.width, self.height)?;
+        Ok(filters::edge_canny::canny_debug(img, config.config())?.into())
+    }
+
+    /// Canny edges traced into point chains; this buffer is left unchanged.
+    pub fn canny_contours(&self, config: &Canny, epsilon: Option<f32>) -> Result<Contours, JsValue> {
+        let img = RgbaImage::new(&self.data, self.width, self.height)?;
+        simplified(filters::edge_canny::canny_contours(img, config.config())?, epsilon)
+    }
+
+    /// Canny edge locations as `[x0, y0, x1, y1, ...]`; this buffer is left unchanged.
+    pub fn canny_edge_points(&self, config: &Canny) -> Result<Vec<f32>, JsValue> {
+        let img = RgbaImage::new(&self.data, self.width, self.height)?;
+        Ok(filters::edge_canny::canny_edge_points(img, config.config())?)
+    }
+
+    /// Trace this edge map into an SVG document string.
+    pub fn edge_mask_svg(&self, epsilon: Option<f32>, stroke: String, stroke_width: f32) -> Result<String, JsValue> {
+        let img = RgbaImage::new(&self.data, self.width, self.height)?;
+        let style = SvgStyle { stroke, stroke_width };
+        Ok(filters::svg::edge_mask_to_svg(img, epsilon, &style)?)
+    }
+}
+
+impl Drop for ImageBuffer {
+    fn drop(&mut self) {
+        memory::record_free(self.data.as_ptr(), self.data.len(), Owner::ImageBuffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::memory::{free, memory_report};
+
+    #[test]
+    fn storage_is_tracked_until_drop() {
+        let buffer = ImageBuffer::from_rgba(&[7u8; 24], 3, 2).unwrap();
+        let ptr = buffer.ptr();
+        let report = memory_report();
+        assert_eq!((report.live_bytes(), report.alloc_count()), (24, 1));
+        assert_eq!((report.block_ptrs(), report.block_sizes()), (vec![ptr as usize], vec![24]));
+
+        // JS may not free storage it does not own
+        free(ptr as *mut u8, 24);
+        let report = memory_report();
+        assert_eq!((report.live_bytes(), report.free_count(), report.outstanding_blocks()), (24, 0, 1));
+        assert_eq!(buffer.data, [7u8; 24]);
+
+        drop(buffer);
+        let report = memory_report();
+        assert_eq!((report.live_bytes(), report.peak_bytes()), (0, 24));
+        assert_eq!((report.free_count(), report.outstanding_blocks()), (1, 0));
+    }
+}
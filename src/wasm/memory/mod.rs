@@ -0,0 +1,319 @@
+use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::error::ImageError;
+
+#[cfg(feature = "track-allocations")]
+mod tracking;
+
+#[cfg(feature = "track-allocations")]
+pub use tracking::{heap_report, HeapReport};
+
+/// Bookkeeping for buffers handed out through `alloc`.
+/// Blocks are keyed by pointer so `free` can release the exact size that was allocated.
+struct Accounting {
+    live_bytes: usize,
+    peak_bytes: usize,
+    alloc_count: u32,
+    free_count: u32,
+    blocks: BTreeMap<usize, Block>,
+}
+
+/// Who owns a tracked block, so `free` can never release storage owned by an `ImageBuffer`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Owner {
+    Js,
+    ImageBuffer,
+}
+
+#[derive(Clone, Copy)]
+struct Block {
+    size: usize,
+    owner: Owner,
+}
+
+thread_local! {
+    static ACCOUNTING: RefCell<Accounting> = const {
+        RefCell::new(Accounting {
+            live_bytes: 0,
+            peak_bytes: 0,
+            alloc_count: 0,
+            free_count: 0,
+            blocks: BTreeMap::new(),
+        })
+    };
+}
+
+/// Record a block that is now owned by JS (via `alloc`) or by an `ImageBuffer`.
+pub(crate) fn record_alloc(ptr: *const u8, size: usize, owner: Owner) {
+    // Zero-sized buffers share a dangling pointer and own no memory, so they are not tracked
+    if size == 0 {
+        return;
+    }
+    ACCOUNTING.with(|acc| {
+        let mut acc = acc.borrow_mut();
+        acc.live_bytes += size;
+        acc.peak_bytes = acc.peak_bytes.max(acc.live_bytes);
+        acc.alloc_count += 1;
+        acc.blocks.insert(ptr as usize, Block { size, owner });
+    });
+}
+
+/// Forget a block previously passed to `record_alloc`. Returns `false`, leaving the
+/// accounting untouched, if the pointer is unknown, belongs to a different owner or was
+/// recorded with a different size.
+pub(crate) fn record_free(ptr: *const u8, size: usize, owner: Owner) -> bool {
+    ACCOUNTING.with(|acc| {
+        let mut acc = acc.borrow_mut();
+        let key = ptr as usize;
+        match acc.blocks.get(&key) {
+            Some(block) if block.owner == owner && block.size == size => {}
+            _ => return false,
+        }
+        acc.blocks.remove(&key);
+        acc.live_bytes -= size;
+        acc.free_count += 1;
+        true
+    })
+}
+
+#[wasm_bindgen]
+pub fn alloc(size: usize) -> *mut u8 {
+    let mut buffer = Vec::with_capacity(size);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+
+    record_alloc(ptr, size, Owner::Js);
+
+    ptr
+}
+
+/// Release a buffer obtained from `alloc`.
+/// Unknown pointers (never allocated, already freed, or owned by an `ImageBuffer`) and
+/// sizes that differ from the one passed to `alloc` are ignored instead of corrupting the heap.
+#[wasm_bindgen]
+pub fn free(ptr: *mut u8, size: usize) {
+    if !record_free(ptr, size, Owner::Js) {
+        return;
+    }
+
+    unsafe {
+        let _ = Vec::from_raw_parts(ptr, 0, size);
+    }
+}
+
+/// Byte length of a `width x height` RGBA image, checked against overflow.
+pub(crate) fn rgba_len(width: u32, height: u32) -> Result<usize, ImageError> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(4))
+        .ok_or(ImageError::Overflow)
+}
+
+/// Bytes available from `ptr` to the end of the tracked block containing it, if any.
+fn available_bytes(ptr: *const u8) -> Option<usize> {
+    let addr = ptr as usize;
+    ACCOUNTING.with(|acc| {
+        let acc = acc.borrow();
+        let (&start, block) = acc.blocks.range(..=addr).next_back()?;
+        let end = start + block.size;
+        if addr < end { Some(end - addr) } else { None }
+    })
+}
+
+/// Validate an RGBA buffer handed in from JS and borrow it as a slice.
+///
+/// Rejects null pointers, empty images and sizes that overflow; filters check their own
+/// kernel-specific minimum size. Pointers into a block from `alloc` (or an `ImageBuffer`)
+/// are also checked against its size; pointers the accounting does not know about are trusted.
+pub(crate) fn rgba_slice_mut<'a>(
+    ptr: *mut u8,
+    width: u32,
+    height: u32
+) -> Result<&'a mut [u8], ImageError> {
+    let needed = rgba_len(width, height)?;
+    checked_slice_mut(ptr, width, height, needed)
+}
+
+/// Like [`rgba_slice_mut`] for a one-byte-per-pixel buffer of `width * height` bytes.
+pub(crate) fn gray_slice_mut<'a>(
+    ptr: *mut u8,
+    width: u32,
+    height: u32
+) -> Result<&'a mut [u8], ImageError> {
+    let needed = (width as usize).checked_mul(height as usize).ok_or(ImageError::Overflow)?;
+    checked_slice_mut(ptr, width, height, needed)
+}
+
+fn checked_slice_mut<'a>(
+    ptr: *mut u8,
+    width: u32,
+    height: u32,
+    needed: usize
+) -> Result<&'a mut [u8], ImageError> {
+    if ptr.is_null() {
+        return Err(ImageError::NullPointer);
+    }
+    if width == 0 || height == 0 {
+        return Err(ImageError::InvalidDimensions { width, height, min: 1 });
+    }
+    if let Some(available) = available_bytes(ptr) {
+        if available < needed {
+            return Err(ImageError::BufferTooSmall { needed, available });
+        }
+    }
+    Ok(unsafe { std::slice::from_raw_parts_mut(ptr, needed) })
+}
+
+#[wasm_bindgen]
+pub fn get_allocated_memory_mb() -> f64 {
+    ACCOUNTING.with(|acc| {
+        let allocated = acc.borrow().live_bytes;
+        (allocated as f64) / (1024.0 * 1024.0)
+    })
+}
+
+/// Snapshot of the `alloc`/`free` accounting at the time `memory_report()` was called.
+#[wasm_bindgen]
+pub struct MemoryReport {
+    live_bytes: usize,
+    peak_bytes: usize,
+    alloc_count: u32,
+    free_count: u32,
+    block_ptrs: Vec<usize>,
+    block_sizes: Vec<usize>,
+}
+
+#[wasm_bindgen]
+impl MemoryReport {
+    /// Bytes currently allocated and not yet freed.
+    #[wasm_bindgen(getter)]
+    pub fn live_bytes(&self) -> usize {
+        self.live_bytes
+    }
+
+    /// Highest `live_bytes` seen since start-up or the last `reset_peak()`.
+    #[wasm_bindgen(getter)]
+    pub fn peak_bytes(&self) -> usize {
+        self.peak_bytes
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn alloc_count(&self) -> u32 {
+        self.alloc_count
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn free_count(&self) -> u32 {
+        self.free_count
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn live_mb(&self) -> f64 {
+        (self.live_bytes as f64) / (1024.0 * 1024.0)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn peak_mb(&self) -> f64 {
+        (self.peak_bytes as f64) / (1024.0 * 1024.0)
+    }
+
+    /// Number of blocks that are still outstanding.
+    #[wasm_bindgen(getter)]
+    pub fn outstanding_blocks(&self) -> usize {
+        self.block_ptrs.len()
+    }
+
+    /// Pointers of the outstanding blocks, ordered by address.
+    pub fn block_ptrs(&self) -> Vec<usize> {
+        self.block_ptrs.clone()
+    }
+
+    /// Sizes of the outstanding blocks, in the same order as `block_ptrs()`.
+    pub fn block_sizes(&self) -> Vec<usize> {
+        self.block_sizes.clone()
+    }
+}
+
+#[wasm_bindgen]
+pub fn memory_report() -> MemoryReport {
+    ACCOUNTING.with(|acc| {
+        let acc = acc.borrow();
+        MemoryReport {
+            live_bytes: acc.live_bytes,
+            peak_bytes: acc.peak_bytes,
+            alloc_count: acc.alloc_count,
+            free_count: acc.free_count,
+            block_ptrs: acc.blocks.keys().copied().collect(),
+            block_sizes: acc.blocks.values().map(|b| b.size).collect(),
+        }
+    })
+}
+
+/// Start a new measurement window: the peak is lowered to the current live size.
+/// With `track-allocations` enabled the global heap peak is reset as well.
+#[wasm_bindgen]
+pub fn reset_peak() {
+    ACCOUNTING.with(|acc| {
+        let mut acc = acc.borrow_mut();
+        acc.peak_bytes = acc.live_bytes;
+    });
+
+    #[cfg(feature = "track-allocations")]
+    tracking::reset_heap_peak();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointer_buffers_are_validated() {
+        assert_eq!(rgba_slice_mut(std::ptr::null_mut(), 2, 2).err(), Some(ImageError::NullPointer));
+
+        let ptr = alloc(16);
+        assert_eq!(rgba_slice_mut(ptr, 0, 2).err(), Some(ImageError::InvalidDimensions { width: 0, height: 2, min: 1 }));
+        assert!(rgba_slice_mut(ptr, 2, 2).is_ok());
+        assert_eq!(rgba_slice_mut(ptr, 3, 2).err(), Some(ImageError::BufferTooSmall { needed: 24, available: 16 }));
+        assert!(gray_slice_mut(ptr, 4, 4).is_ok());
+        assert_eq!(gray_slice_mut(ptr, 5, 4).err(), Some(ImageError::BufferTooSmall { needed: 20, available: 16 }));
+
+        // A wrong size is ignored like an unknown pointer; the block stays tracked
+        free(ptr, 8);
+        assert!(rgba_slice_mut(ptr, 3, 2).is_err());
+        free(ptr, 16);
+    }
+
+    #[test]
+    fn free_releases_what_alloc_recorded() {
+        let a = alloc(100);
+        let b = alloc(50);
+        let report = memory_report();
+        assert_eq!((report.live_bytes(), report.peak_bytes()), (150, 150));
+        assert_eq!((report.alloc_count(), report.free_count()), (2, 0));
+        assert_eq!(report.outstanding_blocks(), 2);
+        assert!(report.block_ptrs().contains(&(a as usize)));
+
+        free(a, 100);
+        let report = memory_report();
+        assert_eq!((report.live_bytes(), report.peak_bytes()), (50, 150));
+        assert_eq!((report.alloc_count(), report.free_count()), (2, 1));
+        assert_eq!(report.block_ptrs(), vec![b as usize]);
+        assert_eq!(report.block_sizes(), vec![50]);
+
+        // Double and wrong-size frees leave the accounting alone
+        free(a, 100);
+        free(b, 49);
+        let report = memory_report();
+        assert_eq!((report.live_bytes(), report.free_count(), report.outstanding_blocks()), (50, 1, 1));
+
+        reset_peak();
+        assert_eq!(memory_report().peak_bytes(), 50);
+
+        free(b, 50);
+        let report = memory_report();
+        assert_eq!((report.live_bytes(), report.peak_bytes()), (0, 50));
+        assert_eq!((report.alloc_count(), report.free_count(), report.outstanding_blocks()), (2, 2, 0));
+    }
+}
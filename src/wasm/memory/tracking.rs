@@ -0,0 +1,124 @@
+use wasm_bindgen::prelude::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// Global allocator wrapper that counts every heap allocation made by the crate,
+/// including the scratch buffers the filters allocate internally (copies, magnitudes, NMS, ...).
+/// `alloc`/`free` accounting only sees the image buffers shared with JS; this sees everything.
+pub struct TrackingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+static ALLOC_COUNT: AtomicU32 = AtomicU32::new(0);
+static FREE_COUNT: AtomicU32 = AtomicU32::new(0);
+
+#[global_allocator]
+static GLOBAL: TrackingAllocator = TrackingAllocator;
+
+#[inline]
+fn record_grow(size: usize) {
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+#[inline]
+fn record_shrink(size: usize) {
+    LIVE_BYTES.fetch_sub(size, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            record_grow(layout.size());
+            ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            record_grow(layout.size());
+            ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        record_shrink(layout.size());
+        FREE_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            // A growing realloc may briefly hold both blocks; only the net change is counted
+            if new_size > layout.size() {
+                record_grow(new_size - layout.size());
+            } else {
+                record_shrink(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Snapshot of every heap allocation made through the global allocator.
+#[wasm_bindgen]
+pub struct HeapReport {
+    live_bytes: usize,
+    peak_bytes: usize,
+    alloc_count: u32,
+    free_count: u32,
+}
+
+#[wasm_bindgen]
+impl HeapReport {
+    /// Heap bytes currently in use by the module.
+    #[wasm_bindgen(getter)]
+    pub fn live_bytes(&self) -> usize {
+        self.live_bytes
+    }
+
+    /// Highest heap usage since start-up or the last `reset_peak()` — the filter's working set.
+    #[wasm_bindgen(getter)]
+    pub fn peak_bytes(&self) -> usize {
+        self.peak_bytes
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn alloc_count(&self) -> u32 {
+        self.alloc_count
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn free_count(&self) -> u32 {
+        self.free_count
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn live_mb(&self) -> f64 {
+        (self.live_bytes as f64) / (1024.0 * 1024.0)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn peak_mb(&self) -> f64 {
+        (self.peak_bytes as f64) / (1024.0 * 1024.0)
+    }
+}
+
+#[wasm_bindgen]
+pub fn heap_report() -> HeapReport {
+    HeapReport {
+        live_bytes: LIVE_BYTES.load(Ordering::Relaxed),
+        peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+        alloc_count: ALLOC_COUNT.load(Ordering::Relaxed),
+        free_count: FREE_COUNT.load(Ordering::Relaxed),
+    }
+}
+
+pub(crate) fn reset_heap_peak() {
+    PEAK_BYTES.store(LIVE_BYTES.load(Ordering::Relaxed), Ordering::Relaxed);
+}
@@ -0,0 +1,27 @@
+//! `#[wasm_bindgen]` shim over the core filters: pointer-based exports for buffers
+//! obtained from `alloc`, plus the owned `ImageBuffer` wrapper.
+
+mod border;
+mod canny;
+mod color_matrix;
+mod contours;
+mod error;
+mod filters;
+mod image_buffer;
+mod memory;
+#[cfg(feature = "profiling")]
+mod profile;
+mod sobel;
+
+pub use border::Border;
+pub use canny::{Canny, CannyDebug};
+pub use color_matrix::ColorMatrix;
+pub use contours::Contours;
+pub use filters::*;
+pub use image_buffer::ImageBuffer;
+pub use sobel::Sobel;
+pub use memory::{alloc, free, get_allocated_memory_mb, memory_report, reset_peak, MemoryReport};
+#[cfg(feature = "track-allocations")]
+pub use memory::{heap_report, HeapReport};
+#[cfg(feature = "profiling")]
+pub use profile::{last_profile, ProfileReport};
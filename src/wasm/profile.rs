@@ -0,0 +1,45 @@
+use wasm_bindgen::prelude::*;
+
+use crate::profile::{self, Profile};
+
+/// Per-stage timings of the last filter call, see `last_profile()`.
+#[wasm_bindgen]
+pub struct ProfileReport {
+    profile: Profile,
+}
+
+#[wasm_bindgen]
+impl ProfileReport {
+    /// Name of the filter that was profiled, e.g. `"edge_detection_canny"`.
+    #[wasm_bindgen(getter)]
+    pub fn filter(&self) -> String {
+        self.profile.filter.to_string()
+    }
+
+    /// Wall time of the whole call, including anything not covered by a stage.
+    #[wasm_bindgen(getter)]
+    pub fn total_ms(&self) -> f64 {
+        self.profile.total_ms
+    }
+
+    /// Stage names in execution order.
+    pub fn stage_names(&self) -> Vec<String> {
+        self.profile.stages.iter().map(|s| s.name.to_string()).collect()
+    }
+
+    /// Stage durations in milliseconds, matching `stage_names()`.
+    pub fn stage_ms(&self) -> Vec<f64> {
+        self.profile.stages.iter().map(|s| s.ms).collect()
+    }
+
+    /// The same data as a JSON string, for logging or `JSON.parse`.
+    pub fn to_json(&self) -> String {
+        self.profile.to_json()
+    }
+}
+
+/// Timings of the most recent filter call, or `undefined` if none ran yet.
+#[wasm_bindgen]
+pub fn last_profile() -> Option<ProfileReport> {
+    profile::last_profile().map(|profile| ProfileReport { profile })
+}
@@ -0,0 +1,67 @@
+use wasm_bindgen::prelude::*;
+
+use crate::filters::border::BorderMode;
+use crate::filters::edge_sobel::SobelMode;
+use crate::filters::gradient::GradientMode;
+use super::border::Border;
+
+/// JS handle for Sobel options, e.g. `Sobel.percentile(0.9)` or
+/// `Sobel.magnitude().with_border(Border.reflect101())`. Borders are skipped and the luma
+/// gradient is used unless set.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct Sobel {
+    mode: SobelMode,
+    border: BorderMode,
+    gradient: GradientMode,
+}
+
+impl Sobel {
+    fn new(mode: SobelMode) -> Sobel {
+        Sobel { mode, border: BorderMode::Skip, gradient: GradientMode::Luma }
+    }
+
+    pub(crate) fn mode(&self) -> SobelMode {
+        self.mode
+    }
+
+    pub(crate) fn border(&self) -> BorderMode {
+        self.border
+    }
+
+    pub(crate) fn gradient(&self) -> GradientMode {
+        self.gradient
+    }
+}
+
+#[wasm_bindgen]
+impl Sobel {
+    /// Binary edges at or above the `p`-th percentile (0..=1) of non-zero magnitudes.
+    pub fn percentile(p: f32) -> Sobel {
+        Sobel::new(SobelMode::Percentile(p))
+    }
+
+    /// Binary edges where the gradient magnitude is at least `threshold`.
+    pub fn absolute(threshold: f32) -> Sobel {
+        Sobel::new(SobelMode::Absolute(threshold))
+    }
+
+    /// Binary edges above the Otsu level of the magnitude histogram.
+    pub fn otsu() -> Sobel {
+        Sobel::new(SobelMode::Otsu)
+    }
+
+    /// Raw gradient magnitude as grayscale, no threshold.
+    pub fn magnitude() -> Sobel {
+        Sobel::new(SobelMode::Magnitude)
+    }
+
+    pub fn with_border(&self, border: &Border) -> Sobel {
+        Sobel { border: border.mode(), ..*self }
+    }
+
+    /// Colour gradient, so edges between colours of equal brightness are found too.
+    pub fn with_gradient(&self, gradient: GradientMode) -> Sobel {
+        Sobel { gradient, ..*self }
+    }
+}